@@ -86,6 +86,82 @@ fn bench_single_loop_times_for_1000_node_tree_within_1000(c: &mut Criterion) {
     );
 }
 
+fn bench_random_queries_on_100_000_node_tree(c: &mut Criterion) {
+    let points = generate_points(100_000);
+    let queries = generate_points(1000);
+    let mut tree = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+    // inserted nodes end up scattered at the end of the node storage.
+    for p in generate_points(20_000) {
+        tree.insert_node(p);
+    }
+
+    c.bench_function("bench_random_queries_on_100_000_node_tree", |b| {
+        b.iter(|| {
+            for q in &queries {
+                tree.nearest_search(q);
+            }
+        })
+    });
+
+    tree.optimize_layout();
+    c.bench_function(
+        "bench_random_queries_on_100_000_node_tree_optimized_layout",
+        |b| {
+            b.iter(|| {
+                for q in &queries {
+                    tree.nearest_search(q);
+                }
+            })
+        },
+    );
+}
+
+fn bench_squared_euclidean_many_10_000_points(c: &mut Criterion) {
+    c.bench_function("bench_squared_euclidean_many_10_000_points", |b| {
+        let points = generate_points(10_000);
+        let query = [0.5, 0.5, 0.5];
+
+        b.iter(|| squared_euclidean_many(&query, &points))
+    });
+}
+
+fn bench_pairs_within_two_10_000_node_trees(c: &mut Criterion) {
+    let a = kdtree::Kdtree::from_vec(generate_points(10_000)).unwrap();
+    let b = kdtree::Kdtree::from_vec(generate_points(10_000)).unwrap();
+    let radius = 0.02;
+
+    let mut group = c.benchmark_group("bench_pairs_within_two_10_000_node_trees");
+    group.bench_function("within_per_point", |bencher| {
+        bencher.iter(|| {
+            a.iter()
+                .map(|p| b.within(p, radius * radius, &squared_euclidean).len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("dual_tree", |bencher| {
+        bencher.iter(|| a.pairs_within(&b, radius).unwrap().len())
+    });
+    group.finish();
+}
+
+fn bench_knn_graph_10_000_node_tree(c: &mut Criterion) {
+    let tree = kdtree::Kdtree::from_vec(generate_points(10_000)).unwrap();
+    let k = 8;
+
+    let mut group = c.benchmark_group("bench_knn_graph_10_000_node_tree");
+    group.bench_function("nearest_k_per_point", |bencher| {
+        bencher.iter(|| {
+            tree.iter()
+                .map(|p| tree.nearest_k_search(p, k + 1).len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("knn_graph", |bencher| {
+        bencher.iter(|| tree.knn_graph(k).neighbors.len())
+    });
+    group.finish();
+}
+
 #[allow(dead_code)]
 fn bench_creating_1000_000_node_tree(c: &mut Criterion) {
     c.bench_function("bench_creating_1000_000_node_tree", |b| {
@@ -133,6 +209,10 @@ criterion_group!(
     bench_single_loop_times_for_100_000_node_tree,
     bench_adding_same_node_to_1000_tree,
     bench_incrementally_building_the_1000_tree,
-    bench_single_loop_times_for_1000_node_tree_within_1000
+    bench_single_loop_times_for_1000_node_tree_within_1000,
+    bench_random_queries_on_100_000_node_tree,
+    bench_squared_euclidean_many_10_000_points,
+    bench_pairs_within_two_10_000_node_trees,
+    bench_knn_graph_10_000_node_tree
 );
 criterion_main!(benches);
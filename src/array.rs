@@ -0,0 +1,135 @@
+//! Building and querying trees straight from `ndarray` matrices holding one point per row.
+//! Available with the `ndarray` cargo feature.
+
+use ndarray::{Array2, ArrayBase, ArrayView2, Data, Ix2};
+
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+/// Row of the matrix a tree was built from. Borrows the row in place, no coordinates are copied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ArrayRow<'a> {
+    /// Index of the row in the source matrix.
+    pub index: usize,
+    row: &'a [f64],
+}
+
+impl<'a> KdtreePointTrait for ArrayRow<'a> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        self.row
+    }
+}
+
+impl<'a> Kdtree<ArrayRow<'a>> {
+    /// Builds the tree over the rows of a `(n, d)` matrix.
+    ///
+    /// Returns `None` if the matrix has no rows, or if it is not in standard (row-major, contiguous)
+    /// layout, in which case `as_standard_layout` can be used to get a copy that is.
+    pub fn from_array2(view: ArrayView2<'a, f64>) -> Option<Kdtree<ArrayRow<'a>>> {
+        let dims = view.ncols();
+        let data = view.to_slice()?;
+
+        let mut rows: Vec<ArrayRow<'a>> = data
+            .chunks(dims.max(1))
+            .enumerate()
+            .map(|(index, row)| ArrayRow { index, row })
+            .collect();
+
+        Kdtree::new(&mut rows)
+    }
+
+    /// Finds the `k` nearest rows for every row of `queries`, `k` is capped at the number of stored rows.
+    ///
+    /// Returns the `(m, k)` matrices of source row indices and euclidean distances,
+    /// nearest first, like scikit-learn's `KDTree.query`. Fails with `DimensionMismatch`
+    /// when `queries` has another number of columns than the tree's points.
+    pub fn query_k<S>(
+        &self,
+        queries: &ArrayBase<S, Ix2>,
+        k: usize,
+    ) -> Result<(Array2<usize>, Array2<f64>), KdtreeError>
+    where
+        S: Data<Elem = f64>,
+    {
+        let dims = self.nodes[0].point.dims().len();
+        if queries.ncols() != dims {
+            return Err(KdtreeError::DimensionMismatch {
+                expected: dims,
+                actual: queries.ncols(),
+            });
+        }
+
+        let k = k.min(self.nodes.len());
+        let mut indices = Array2::zeros((queries.nrows(), k));
+        let mut distances = Array2::zeros((queries.nrows(), k));
+        let mut query = Vec::with_capacity(queries.ncols());
+
+        for (i, row) in queries.outer_iter().enumerate() {
+            query.clear();
+            query.extend(row.iter());
+
+            let nearest = self.nearest_k_search_by_dims(&query, k);
+            for (j, (point, distance)) in nearest.into_iter().enumerate() {
+                indices[[i, j]] = point.index;
+                distances[[i, j]] = distance.sqrt();
+            }
+        }
+
+        Ok((indices, distances))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{array, ShapeBuilder};
+
+    #[test]
+    fn builds_from_rows_and_answers_with_row_indices() {
+        let points = array![[0., 0.], [10., 0.], [0., 10.], [1., 1.]];
+        let tree = Kdtree::from_array2(points.view()).unwrap();
+
+        let (indices, distances) = tree.query_k(&array![[0.9, 0.9], [9., 1.]], 2).unwrap();
+
+        assert_eq!(array![[3, 0], [1, 3]], indices);
+        assert!((distances[[0, 0]] - 0.02f64.sqrt()).abs() < 1e-12);
+        assert!((distances[[1, 0]] - 2f64.sqrt()).abs() < 1e-12);
+        assert!((distances[[1, 1]] - 8.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_empty_and_non_standard_layout_matrices() {
+        let empty = Array2::<f64>::zeros((0, 3));
+        assert!(Kdtree::from_array2(empty.view()).is_none());
+
+        let column_major = Array2::<f64>::zeros((4, 3).f());
+        assert!(Kdtree::from_array2(column_major.view()).is_none());
+
+        let copy = column_major.as_standard_layout();
+        assert!(Kdtree::from_array2(copy.view()).is_some());
+    }
+
+    #[test]
+    fn query_k_caps_k_at_the_stored_points() {
+        let points = array![[0., 0.], [10., 0.]];
+        let tree = Kdtree::from_array2(points.view()).unwrap();
+
+        let (indices, distances) = tree.query_k(&array![[0., 0.]], usize::MAX).unwrap();
+        assert_eq!(array![[0, 1]], indices);
+        assert_eq!(array![[0., 10.]], distances);
+    }
+
+    #[test]
+    fn query_k_rejects_queries_of_another_width() {
+        let points = array![[0., 0.], [10., 0.]];
+        let tree = Kdtree::from_array2(points.view()).unwrap();
+
+        match tree.query_k(&array![[0., 0., 0.]], 1) {
+            Err(KdtreeError::DimensionMismatch { expected, actual }) => {
+                assert_eq!((2, 3), (expected, actual))
+            }
+            _ => panic!("answered a query of the wrong width"),
+        }
+    }
+}
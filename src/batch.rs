@@ -0,0 +1,172 @@
+//! Batch queries answering many query points against one tree.
+//! The results are the same as calling the single query functions in a loop, in query order.
+//! With the `rayon` feature the `par_` versions spread the queries over the rayon thread pool,
+//! they need the points to be `Send + Sync`.
+
+use kdtree::{Kdtree, KdtreePointTrait};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// `nearest_search` for every point in `queries`.
+    pub fn nearest_batch(&self, queries: &[KdtreePoint]) -> Vec<KdtreePoint> {
+        queries.iter().map(|q| self.nearest_search(q)).collect()
+    }
+
+    /// `nearest_k_search` for every point in `queries`.
+    pub fn nearest_k_batch(
+        &self,
+        queries: &[KdtreePoint],
+        k: usize,
+    ) -> Vec<Vec<(&KdtreePoint, f64)>> {
+        queries
+            .iter()
+            .map(|q| self.nearest_k_search(q, k))
+            .collect()
+    }
+
+    /// `within` for every point in `queries`.
+    pub fn within_batch<F>(
+        &self,
+        queries: &[KdtreePoint],
+        search_radius: f64,
+        distance_function: &F,
+    ) -> Vec<Vec<&KdtreePoint>>
+    where
+        F: Fn(&[f64], &[f64]) -> f64,
+    {
+        queries
+            .iter()
+            .map(|q| self.within(q, search_radius, distance_function))
+            .collect()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<KdtreePoint: KdtreePointTrait + Send + Sync> Kdtree<KdtreePoint> {
+    /// `nearest_batch` with the queries answered in parallel.
+    pub fn par_nearest_batch(&self, queries: &[KdtreePoint]) -> Vec<KdtreePoint> {
+        queries.par_iter().map(|q| self.nearest_search(q)).collect()
+    }
+
+    /// `nearest_k_batch` with the queries answered in parallel.
+    pub fn par_nearest_k_batch(
+        &self,
+        queries: &[KdtreePoint],
+        k: usize,
+    ) -> Vec<Vec<(&KdtreePoint, f64)>> {
+        queries
+            .par_iter()
+            .map(|q| self.nearest_k_search(q, k))
+            .collect()
+    }
+
+    /// `within_batch` with the queries answered in parallel.
+    pub fn par_within_batch<F>(
+        &self,
+        queries: &[KdtreePoint],
+        search_radius: f64,
+        distance_function: &F,
+    ) -> Vec<Vec<&KdtreePoint>>
+    where
+        F: Fn(&[f64], &[f64]) -> f64 + Sync,
+    {
+        queries
+            .par_iter()
+            .map(|q| self.within(q, search_radius, distance_function))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::squared_euclidean;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use rand::Rng;
+    use test_common::Point3WithId;
+
+    fn random_points(count: usize) -> Vec<Point3WithId> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|i| {
+                Point3WithId::new(
+                    i as i32,
+                    rng.gen_range(0., 100.),
+                    rng.gen_range(0., 100.),
+                    rng.gen_range(0., 100.),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batches_match_single_queries_in_order() {
+        let tree = Kdtree::new(&mut random_points(1000)).unwrap();
+        let queries = random_points(200);
+
+        let nearest: Vec<_> = queries.iter().map(|q| tree.nearest_search(q)).collect();
+        assert_eq!(nearest, tree.nearest_batch(&queries));
+
+        let nearest_k: Vec<_> = queries
+            .iter()
+            .map(|q| tree.nearest_k_search(q, 5))
+            .collect();
+        assert_eq!(nearest_k, tree.nearest_k_batch(&queries, 5));
+
+        let within: Vec<_> = queries
+            .iter()
+            .map(|q| tree.within(q, 100., &squared_euclidean))
+            .collect();
+        assert_eq!(
+            within,
+            tree.within_batch(&queries, 100., &squared_euclidean)
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_batches_match_serial_ones() {
+        let tree = Kdtree::new(&mut random_points(1000)).unwrap();
+        let queries = random_points(200);
+
+        assert_eq!(
+            tree.nearest_batch(&queries),
+            tree.par_nearest_batch(&queries)
+        );
+        assert_eq!(
+            tree.nearest_k_batch(&queries, 5),
+            tree.par_nearest_k_batch(&queries, 5)
+        );
+        assert_eq!(
+            tree.within_batch(&queries, 100., &squared_euclidean),
+            tree.par_within_batch(&queries, 100., &squared_euclidean)
+        );
+    }
+
+    #[test]
+    fn batches_work_with_points_that_are_not_thread_safe() {
+        use std::rc::Rc;
+
+        #[derive(Clone, PartialEq)]
+        struct Shared(Rc<[f64; 2]>);
+        impl KdtreePointTrait for Shared {
+            fn dims(&self) -> &[f64] {
+                &self.0[..]
+            }
+        }
+
+        let points = vec![Shared(Rc::new([0., 0.])), Shared(Rc::new([4., 1.]))];
+        let tree = Kdtree::from_vec(points.clone()).unwrap();
+        assert!(tree.nearest_batch(&points) == points);
+    }
+
+    #[test]
+    fn empty_batch_gives_no_results() {
+        let tree = Kdtree::new(&mut random_points(10)).unwrap();
+
+        assert!(tree.nearest_batch(&[]).is_empty());
+        assert!(tree.nearest_k_batch(&[], 3).is_empty());
+        assert!(tree.within_batch(&[], 1., &squared_euclidean).is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+//! Command line access to the kdtree library: build a tree from a point file, save it
+//! and answer nearest, k nearest, radius and box queries on it.
+
+extern crate kdtree;
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use serde_json::{json, Value};
+
+use kdtree::binary::ViewPoint;
+use kdtree::distance::{euclidean, squared_euclidean};
+use kdtree::pointcloud::{CsvOptions, PointCloud};
+use kdtree::KdtreeView;
+
+const USAGE: &str = "usage:
+    kdtree build <points.csv|xyz|ply> [csv options] -o <tree.kdt>
+    kdtree query <tree.kdt|points.csv|xyz|ply> [csv options] [--format json|csv] <query>
+    kdtree query <tree.kdt|points.csv|xyz|ply> [csv options] [--format json|csv] --stdin
+
+csv options:
+    --no-header        the first line is a point, not column names
+    --columns <x,y,z>  0 based columns of the coordinates, 0,1,2 by default
+
+queries, coordinates are comma separated:
+    nearest <x,y,z>
+    knn <k> <x,y,z>
+    radius <r> <x,y,z>
+    box <min_x,min_y,min_z> <max_x,max_y,max_z>
+
+with --stdin every line is a JSON object, e.g.
+    {\"op\": \"nearest\", \"point\": [1, 2, 3]}
+    {\"op\": \"knn\", \"k\": 5, \"point\": [1, 2, 3]}
+    {\"op\": \"radius\", \"r\": 0.5, \"point\": [1, 2, 3]}
+    {\"op\": \"box\", \"min\": [0, 0, 0], \"max\": [1, 1, 1]}";
+
+const TREE_MAGIC: &[u8] = b"KDTREE";
+
+#[derive(Debug, PartialEq)]
+enum Query {
+    Nearest(Vec<f64>),
+    Knn(usize, Vec<f64>),
+    Radius(f64, Vec<f64>),
+    Box(Vec<f64>, Vec<f64>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum OutputFormat {
+    Json,
+    Csv,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(message) = run(&args) {
+        eprintln!("kdtree: {}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(|a| a.as_str()) {
+        Some("build") => build(&args[1..]),
+        Some("query") => query(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown command {}\n\n{}", other, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let (csv_options, args) = take_csv_options(args)?;
+    let (input, output) = match args.as_slice() {
+        [input, flag, output] if flag == "-o" || flag == "--output" => (input, output),
+        _ => return Err(format!("build expects <input> -o <output>\n\n{}", USAGE)),
+    };
+
+    let bytes = tree_bytes_from_points(input, &csv_options)?;
+    fs::write(output, bytes).map_err(|e| format!("{}: {}", output, e))
+}
+
+fn query(args: &[String]) -> Result<(), String> {
+    let (csv_options, args) = take_csv_options(args)?;
+    let path = args
+        .first()
+        .ok_or_else(|| format!("query expects a tree or point file\n\n{}", USAGE))?;
+
+    let mut format = OutputFormat::Json;
+    let mut rest = &args[1..];
+    if rest.len() >= 2 && rest[0] == "--format" {
+        format = match rest[1].as_str() {
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            other => return Err(format!("unknown output format {}", other)),
+        };
+        rest = &rest[2..];
+    }
+
+    let bytes = load_tree_bytes(path, &csv_options)?;
+    let view = KdtreeView::new(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if format == OutputFormat::Csv {
+        let dims = (0..view.dims())
+            .map(|d| format!(",d{}", d))
+            .collect::<String>();
+        writeln!(out, "query,id,distance{}", dims).map_err(|e| e.to_string())?;
+    }
+
+    if rest == ["--stdin"] {
+        let stdin = io::stdin();
+        // queries are numbered without the blank lines, errors still name the line.
+        let mut query_index = 0;
+        for (line_index, line) in stdin.lock().lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let query = parse_json_query(&line)
+                .and_then(|q| check_dims(q, view.dims()))
+                .map_err(|e| format!("stdin line {}: {}", line_index + 1, e))?;
+            answer(&view, &query, query_index, format, &mut out)?;
+            query_index += 1;
+        }
+    } else {
+        let query = parse_query_args(rest).and_then(|q| check_dims(q, view.dims()))?;
+        answer(&view, &query, 0, format, &mut out)?;
+    }
+
+    Ok(())
+}
+
+// Removes `--no-header` and `--columns x,y,z` from `args`, returning the options they set
+// and the remaining arguments.
+fn take_csv_options(args: &[String]) -> Result<(CsvOptions, Vec<String>), String> {
+    let mut options = CsvOptions::default();
+    let mut rest = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-header" => options.has_header = false,
+            "--columns" => {
+                let columns = args
+                    .next()
+                    .ok_or_else(|| "--columns expects <x,y,z>".to_string())?;
+                let columns: Vec<usize> = columns
+                    .split(',')
+                    .map(|c| c.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("{} are not valid columns", columns))?;
+                match columns.as_slice() {
+                    [x, y, z] => {
+                        options.x_column = *x;
+                        options.y_column = *y;
+                        options.z_column = *z;
+                    }
+                    _ => return Err("--columns expects three columns".to_string()),
+                }
+            }
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    Ok((options, rest))
+}
+
+// Saved trees are used as they are, point files are built into a tree on the fly.
+fn load_tree_bytes(path: &str, csv_options: &CsvOptions) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    if bytes.starts_with(TREE_MAGIC) {
+        Ok(bytes)
+    } else {
+        tree_bytes_from_points(path, csv_options)
+    }
+}
+
+fn tree_bytes_from_points(path: &str, csv_options: &CsvOptions) -> Result<Vec<u8>, String> {
+    let cloud = PointCloud::open_with_csv_options(path, csv_options)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let tree = cloud.build_tree().map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut bytes = vec![];
+    tree.write_binary(&mut bytes, |p| p.index as u64)
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    Ok(bytes)
+}
+
+fn answer<W: Write>(
+    view: &KdtreeView,
+    query: &Query,
+    query_index: usize,
+    format: OutputFormat,
+    out: &mut W,
+) -> Result<(), String> {
+    let results: Vec<(ViewPoint, Option<f64>)> = match query {
+        Query::Nearest(point) => {
+            let found = view.nearest_search(point).map_err(|e| e.to_string())?;
+            let distance = squared_euclidean(point, &found.dims).sqrt();
+            vec![(found, Some(distance))]
+        }
+        Query::Knn(k, point) => view
+            .nearest_k_search(point, *k)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(p, distance)| (p, Some(distance.sqrt())))
+            .collect(),
+        Query::Radius(r, point) => {
+            let mut found: Vec<(ViewPoint, Option<f64>)> = view
+                .within(point, *r, &euclidean)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|p| {
+                    let distance = euclidean(point, &p.dims);
+                    (p, Some(distance))
+                })
+                .collect();
+            found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            found
+        }
+        Query::Box(min, max) => view
+            .within_box(min, max)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|p| (p, None))
+            .collect(),
+    };
+
+    let written = match format {
+        OutputFormat::Json => {
+            let results: Vec<Value> = results
+                .iter()
+                .map(|(p, distance)| match distance {
+                    Some(distance) => json!({"id": p.id, "point": p.dims, "distance": distance}),
+                    None => json!({"id": p.id, "point": p.dims}),
+                })
+                .collect();
+            writeln!(out, "{}", json!({"query": query_index, "results": results}))
+        }
+        OutputFormat::Csv => results.iter().try_for_each(|(p, distance)| {
+            let distance = distance.map(|d| d.to_string()).unwrap_or_default();
+            let coords = p.dims.iter().map(|c| format!(",{}", c)).collect::<String>();
+            writeln!(out, "{},{},{}{}", query_index, p.id, distance, coords)
+        }),
+    };
+
+    written.map_err(|e| e.to_string())
+}
+
+fn parse_query_args(args: &[String]) -> Result<Query, String> {
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+
+    match args.as_slice() {
+        ["nearest", point] => Ok(Query::Nearest(parse_point(point)?)),
+        ["knn", k, point] => Ok(Query::Knn(
+            k.parse().map_err(|_| format!("{} is not a valid k", k))?,
+            parse_point(point)?,
+        )),
+        ["radius", r, point] => Ok(Query::Radius(
+            r.parse()
+                .map_err(|_| format!("{} is not a valid radius", r))?,
+            parse_point(point)?,
+        )),
+        ["box", min, max] => Ok(Query::Box(parse_point(min)?, parse_point(max)?)),
+        _ => Err(format!("could not understand the query\n\n{}", USAGE)),
+    }
+}
+
+fn parse_point(text: &str) -> Result<Vec<f64>, String> {
+    text.split(',')
+        .map(|c| {
+            c.trim()
+                .parse()
+                .map_err(|_| format!("{:?} is not a valid coordinate", c))
+        })
+        .collect()
+}
+
+fn parse_json_query(line: &str) -> Result<Query, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+
+    let point = |key: &str| -> Result<Vec<f64>, String> {
+        value[key]
+            .as_array()
+            .ok_or_else(|| format!("missing {} array", key))?
+            .iter()
+            .map(|c| c.as_f64().ok_or_else(|| format!("{} is not a number", c)))
+            .collect()
+    };
+    let number = |key: &str| -> Result<f64, String> {
+        value[key]
+            .as_f64()
+            .ok_or_else(|| format!("missing number {}", key))
+    };
+
+    match value["op"].as_str() {
+        Some("nearest") => Ok(Query::Nearest(point("point")?)),
+        Some("knn") => {
+            let k = value["k"]
+                .as_u64()
+                .ok_or_else(|| "missing integer k".to_string())?;
+            Ok(Query::Knn(k as usize, point("point")?))
+        }
+        Some("radius") => Ok(Query::Radius(number("r")?, point("point")?)),
+        Some("box") => Ok(Query::Box(point("min")?, point("max")?)),
+        Some(other) => Err(format!("unknown op {}", other)),
+        None => Err("missing op".to_string()),
+    }
+}
+
+fn check_dims(query: Query, dims: usize) -> Result<Query, String> {
+    let points: Vec<&Vec<f64>> = match &query {
+        Query::Nearest(p) | Query::Knn(_, p) | Query::Radius(_, p) => vec![p],
+        Query::Box(min, max) => vec![min, max],
+    };
+
+    match points.iter().find(|p| p.len() != dims) {
+        Some(p) => Err(format!(
+            "query has {} coordinates but the tree has {} dimensions",
+            p.len(),
+            dims
+        )),
+        None => Ok(query),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_command_line_queries() {
+        assert_eq!(
+            Ok(Query::Nearest(vec![1., 2., 3.])),
+            parse_query_args(&args("nearest 1,2,3"))
+        );
+        assert_eq!(
+            Ok(Query::Knn(4, vec![1., 2.])),
+            parse_query_args(&args("knn 4 1,2"))
+        );
+        assert_eq!(
+            Ok(Query::Box(vec![0., 0.], vec![1., 1.])),
+            parse_query_args(&args("box 0,0 1,1"))
+        );
+        assert!(parse_query_args(&args("knn x 1,2")).is_err());
+        assert!(parse_query_args(&args("nearest 1,y")).is_err());
+    }
+
+    #[test]
+    fn parses_json_queries() {
+        assert_eq!(
+            Ok(Query::Radius(0.5, vec![1., 2., 3.])),
+            parse_json_query(r#"{"op": "radius", "r": 0.5, "point": [1, 2, 3]}"#)
+        );
+        assert!(parse_json_query(r#"{"op": "knn", "point": [1, 2, 3]}"#).is_err());
+        assert!(parse_json_query("not json").is_err());
+    }
+
+    #[test]
+    fn takes_csv_options_out_of_the_arguments() {
+        let (options, rest) = take_csv_options(&args(
+            "points.csv --no-header --columns 2,0,1 nearest 1,2,3",
+        ))
+        .unwrap();
+        assert!(!options.has_header);
+        assert_eq!(
+            (2, 0, 1),
+            (options.x_column, options.y_column, options.z_column)
+        );
+        assert_eq!(args("points.csv nearest 1,2,3"), rest);
+
+        let (options, _) = take_csv_options(&args("points.csv -o tree.kdt")).unwrap();
+        assert!(options.has_header);
+        assert!(take_csv_options(&args("points.csv --columns 0,1")).is_err());
+        assert!(take_csv_options(&args("points.csv --columns")).is_err());
+    }
+
+    #[test]
+    fn rejects_queries_of_wrong_dimension() {
+        assert!(check_dims(Query::Nearest(vec![1., 2.]), 3).is_err());
+        assert!(check_dims(Query::Box(vec![1., 2., 3.], vec![1., 2.]), 3).is_err());
+        assert!(check_dims(Query::Nearest(vec![1., 2., 3.]), 3).is_ok());
+    }
+}
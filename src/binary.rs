@@ -0,0 +1,760 @@
+//! Versioned binary format for built trees, queryable in place through `KdtreeView`.
+//!
+//! The file is a fixed header followed by one record per tree node, in the order of the
+//! tree's node storage. Every field is 8 bytes wide and written in native byte order,
+//! so a memory-mapped file can be queried directly by any number of processes.
+//!
+//! Header: magic `KDTREE\0\0`, endianness marker (u32), format version (u32),
+//! dimension count (u64), node count (u64), FNV-1a checksum of all node records (u64).
+//!
+//! Node record: left child (u64), right child (u64), split dimension (u64),
+//! split value (f64), point id (u64), followed by the point coordinates (f64 each).
+//! Missing children are stored as `u64::MAX`.
+
+use std::io::Write;
+
+use distance::squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+const MAGIC: [u8; 8] = *b"KDTREE\0\0";
+const FORMAT_VERSION: u32 = 1;
+const ENDIANNESS_MARKER: u32 = 0x0102_0304;
+const HEADER_LEN: usize = 40;
+const NODE_FIXED_FIELDS: usize = 5;
+const NO_CHILD: u64 = u64::MAX;
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Writes the tree in the format read by `KdtreeView`.
+    ///
+    /// Only coordinates are stored, `point_id` maps each point to the 64-bit id
+    /// that queries on the view answer with. All points must have the same dimension count.
+    pub fn write_binary<W, F>(&self, writer: &mut W, point_id: F) -> Result<(), KdtreeError>
+    where
+        W: Write,
+        F: Fn(&KdtreePoint) -> u64,
+    {
+        let dims = self.nodes[0].point.dims().len();
+        let mut body = Vec::with_capacity(self.nodes.len() * record_len(dims));
+
+        for node in &self.nodes {
+            let coords = node.point.dims();
+            if coords.len() != dims {
+                return Err(KdtreeError::DimensionMismatch {
+                    expected: dims,
+                    actual: coords.len(),
+                });
+            }
+
+            body.extend_from_slice(&node.left_node.map_or(NO_CHILD, |i| i as u64).to_ne_bytes());
+            body.extend_from_slice(&node.right_node.map_or(NO_CHILD, |i| i as u64).to_ne_bytes());
+            body.extend_from_slice(&(node.dimension as u64).to_ne_bytes());
+            body.extend_from_slice(&node.split_on.to_ne_bytes());
+            body.extend_from_slice(&point_id(&node.point).to_ne_bytes());
+            for coord in coords {
+                body.extend_from_slice(&coord.to_ne_bytes());
+            }
+        }
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&ENDIANNESS_MARKER.to_ne_bytes());
+        header.extend_from_slice(&FORMAT_VERSION.to_ne_bytes());
+        header.extend_from_slice(&(dims as u64).to_ne_bytes());
+        header.extend_from_slice(&(self.nodes.len() as u64).to_ne_bytes());
+        header.extend_from_slice(&fnv1a(&body).to_ne_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(&body)?;
+
+        Ok(())
+    }
+}
+
+/// Point answered by `KdtreeView` queries: the stored id and a copy of its coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewPoint {
+    pub id: u64,
+    pub dims: Vec<f64>,
+}
+
+impl KdtreePointTrait for ViewPoint {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+/// Read-only tree over bytes in the format written by `Kdtree::write_binary`,
+/// typically a memory-mapped file. Nodes are decoded lazily as queries visit them.
+pub struct KdtreeView<'a> {
+    records: &'a [u8],
+    dims: usize,
+    node_count: usize,
+}
+
+struct NodeRecord {
+    left_node: Option<usize>,
+    right_node: Option<usize>,
+    dimension: usize,
+    split_on: f64,
+}
+
+impl<'a> KdtreeView<'a> {
+    /// Checks the header, the checksum and the tree structure, then gives access to the tree.
+    pub fn new(bytes: &'a [u8]) -> Result<KdtreeView<'a>, KdtreeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(KdtreeError::Truncated {
+                expected: HEADER_LEN,
+                actual: bytes.len(),
+            });
+        }
+        if bytes[0..8] != MAGIC {
+            return Err(KdtreeError::BadMagic);
+        }
+
+        let marker = read_u32(bytes, 8);
+        if marker == ENDIANNESS_MARKER.swap_bytes() {
+            return Err(KdtreeError::EndiannessMismatch);
+        } else if marker != ENDIANNESS_MARKER {
+            return Err(KdtreeError::InvalidFormat(format!(
+                "unknown endianness marker {:#010x}",
+                marker
+            )));
+        }
+
+        let version = read_u32(bytes, 12);
+        if version != FORMAT_VERSION {
+            return Err(KdtreeError::UnsupportedVersion(version));
+        }
+
+        let dims = read_u64(bytes, 16) as usize;
+        let node_count = read_u64(bytes, 24) as usize;
+        let checksum = read_u64(bytes, 32);
+        if dims == 0 || node_count == 0 {
+            return Err(KdtreeError::InvalidFormat(format!(
+                "tree with {} dimensions and {} nodes",
+                dims, node_count
+            )));
+        }
+
+        let expected_len = node_count
+            .checked_mul(record_len(dims))
+            .and_then(|len| len.checked_add(HEADER_LEN))
+            .ok_or_else(|| KdtreeError::InvalidFormat("node count overflows".to_string()))?;
+        if bytes.len() < expected_len {
+            return Err(KdtreeError::Truncated {
+                expected: expected_len,
+                actual: bytes.len(),
+            });
+        } else if bytes.len() > expected_len {
+            return Err(KdtreeError::InvalidFormat(format!(
+                "{} trailing bytes after the last node",
+                bytes.len() - expected_len
+            )));
+        }
+
+        let records = &bytes[HEADER_LEN..];
+        let actual_checksum = fnv1a(records);
+        if actual_checksum != checksum {
+            return Err(KdtreeError::ChecksumMismatch {
+                expected: checksum,
+                actual: actual_checksum,
+            });
+        }
+
+        let view = KdtreeView {
+            records,
+            dims,
+            node_count,
+        };
+        view.check_structure()?;
+
+        Ok(view)
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    pub fn len(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_count == 0
+    }
+
+    /// Queries fail with `DimensionMismatch` unless `query` has the stored points' dimension count.
+    pub fn nearest_search(&self, query: &[f64]) -> Result<ViewPoint, KdtreeError> {
+        self.check_query(query)?;
+        let mut coords = vec![0.; self.dims];
+
+        let mut nearest_neighbor = 0usize;
+        self.read_coords(0, &mut coords);
+        let mut best_distance = squared_euclidean(query, &coords);
+        self.nearest_search_impl(
+            query,
+            0usize,
+            &mut best_distance,
+            &mut nearest_neighbor,
+            &mut coords,
+        );
+
+        Ok(self.point(nearest_neighbor))
+    }
+
+    /// Returns up to `k` points closest to `query`, nearest first, with their squared euclidean distances.
+    pub fn nearest_k_search(
+        &self,
+        query: &[f64],
+        k: usize,
+    ) -> Result<Vec<(ViewPoint, f64)>, KdtreeError> {
+        self.check_query(query)?;
+        let mut coords = vec![0.; self.dims];
+
+        let k = k.min(self.node_count);
+        let mut nearest = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.nearest_k_search_impl(query, 0usize, k, &mut nearest, &mut coords);
+        }
+
+        Ok(nearest
+            .into_iter()
+            .map(|(index, distance)| (self.point(index), distance))
+            .collect())
+    }
+
+    pub fn within<F>(
+        &self,
+        query: &[f64],
+        search_radius: f64,
+        distance_function: &F,
+    ) -> Result<Vec<ViewPoint>, KdtreeError>
+    where
+        F: Fn(&[f64], &[f64]) -> f64,
+    {
+        self.check_query(query)?;
+        let mut coords = vec![0.; self.dims];
+
+        let mut within_nodes = vec![];
+        self.within_impl(
+            query,
+            0usize,
+            search_radius,
+            distance_function,
+            &mut within_nodes,
+            &mut coords,
+        );
+
+        within_nodes.sort_unstable();
+        Ok(within_nodes
+            .into_iter()
+            .map(|index| self.point(index))
+            .collect())
+    }
+
+    /// Returns the points lying inside the axis aligned box spanned by `min` and `max`, boundaries included.
+    pub fn within_box(&self, min: &[f64], max: &[f64]) -> Result<Vec<ViewPoint>, KdtreeError> {
+        self.check_query(min)?;
+        self.check_query(max)?;
+        let mut coords = vec![0.; self.dims];
+
+        let mut found = vec![];
+        self.within_box_impl(min, max, 0usize, &mut found, &mut coords);
+
+        Ok(found.into_iter().map(|index| self.point(index)).collect())
+    }
+
+    fn check_query(&self, query: &[f64]) -> Result<(), KdtreeError> {
+        if query.len() != self.dims {
+            return Err(KdtreeError::DimensionMismatch {
+                expected: self.dims,
+                actual: query.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn nearest_search_impl(
+        &self,
+        p: &[f64],
+        searched_index: usize,
+        best_distance_squared: &mut f64,
+        best_leaf_found: &mut usize,
+        coords: &mut [f64],
+    ) {
+        let node = self.node(searched_index);
+
+        let splitting_value = node.split_on;
+        let point_splitting_dim_value = p[node.dimension];
+
+        let (closer_node, farther_node) = if point_splitting_dim_value <= splitting_value {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.nearest_search_impl(
+                p,
+                closer_node,
+                best_distance_squared,
+                best_leaf_found,
+                coords,
+            );
+        }
+
+        self.read_coords(searched_index, coords);
+        let distance = squared_euclidean(p, coords);
+        if distance < *best_distance_squared {
+            *best_distance_squared = distance;
+            *best_leaf_found = searched_index;
+        }
+
+        if let Some(farther_node) = farther_node {
+            let distance_on_single_dimension =
+                squared_euclidean(&[splitting_value], &[point_splitting_dim_value]);
+
+            if distance_on_single_dimension <= *best_distance_squared {
+                self.nearest_search_impl(
+                    p,
+                    farther_node,
+                    best_distance_squared,
+                    best_leaf_found,
+                    coords,
+                );
+            }
+        }
+    }
+
+    fn nearest_k_search_impl(
+        &self,
+        p: &[f64],
+        searched_index: usize,
+        k: usize,
+        nearest: &mut Vec<(usize, f64)>,
+        coords: &mut [f64],
+    ) {
+        let node = self.node(searched_index);
+
+        let splitting_value = node.split_on;
+        let point_splitting_dim_value = p[node.dimension];
+
+        let (closer_node, farther_node) = if point_splitting_dim_value <= splitting_value {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.nearest_k_search_impl(p, closer_node, k, nearest, coords);
+        }
+
+        self.read_coords(searched_index, coords);
+        let distance = squared_euclidean(p, coords);
+        if nearest.len() < k || distance < nearest[nearest.len() - 1].1 {
+            let insert_at = nearest
+                .iter()
+                .position(|&(_, d)| distance < d)
+                .unwrap_or(nearest.len());
+            nearest.insert(insert_at, (searched_index, distance));
+            nearest.truncate(k);
+        }
+
+        if let Some(farther_node) = farther_node {
+            let distance_on_single_dimension =
+                squared_euclidean(&[splitting_value], &[point_splitting_dim_value]);
+
+            if nearest.len() < k || distance_on_single_dimension <= nearest[nearest.len() - 1].1 {
+                self.nearest_k_search_impl(p, farther_node, k, nearest, coords);
+            }
+        }
+    }
+
+    fn within_impl<F>(
+        &self,
+        p: &[f64],
+        searched_index: usize,
+        search_radius: f64,
+        distance_function: &F,
+        within_nodes: &mut Vec<usize>,
+        coords: &mut [f64],
+    ) where
+        F: Fn(&[f64], &[f64]) -> f64,
+    {
+        let node = self.node(searched_index);
+
+        let splitting_value = node.split_on;
+        let point_splitting_dim_value = p[node.dimension];
+
+        let (closer_node, farther_node) = if point_splitting_dim_value <= splitting_value {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.within_impl(
+                p,
+                closer_node,
+                search_radius,
+                distance_function,
+                within_nodes,
+                coords,
+            );
+        }
+
+        self.read_coords(searched_index, coords);
+        if distance_function(p, coords) <= search_radius {
+            within_nodes.push(searched_index);
+        }
+
+        if let Some(farther_node) = farther_node {
+            let distance_on_single_dimension =
+                distance_function(&[splitting_value], &[point_splitting_dim_value]);
+
+            if distance_on_single_dimension <= search_radius {
+                self.within_impl(
+                    p,
+                    farther_node,
+                    search_radius,
+                    distance_function,
+                    within_nodes,
+                    coords,
+                );
+            }
+        }
+    }
+
+    fn within_box_impl(
+        &self,
+        min: &[f64],
+        max: &[f64],
+        searched_index: usize,
+        found: &mut Vec<usize>,
+        coords: &mut [f64],
+    ) {
+        let node = self.node(searched_index);
+
+        if let Some(left_node) = node.left_node {
+            if min[node.dimension] <= node.split_on {
+                self.within_box_impl(min, max, left_node, found, coords);
+            }
+        }
+
+        self.read_coords(searched_index, coords);
+        if (0..min.len()).all(|i| min[i] <= coords[i] && coords[i] <= max[i]) {
+            found.push(searched_index);
+        }
+
+        if let Some(right_node) = node.right_node {
+            if max[node.dimension] >= node.split_on {
+                self.within_box_impl(min, max, right_node, found, coords);
+            }
+        }
+    }
+
+    // Every node has to be reachable from the root exactly once, otherwise queries could loop forever.
+    fn check_structure(&self) -> Result<(), KdtreeError> {
+        let mut visited = vec![false; self.node_count];
+        let mut to_visit = vec![0usize];
+
+        while let Some(index) = to_visit.pop() {
+            if visited[index] {
+                return Err(KdtreeError::InvalidFormat(format!(
+                    "node {} is reachable more than once",
+                    index
+                )));
+            }
+            visited[index] = true;
+
+            let offset = index * record_len(self.dims);
+            for child_offset in [offset, offset + 8] {
+                let child = read_u64(self.records, child_offset);
+                if child == NO_CHILD {
+                    continue;
+                }
+                if child >= self.node_count as u64 {
+                    return Err(KdtreeError::InvalidFormat(format!(
+                        "node {} points at child {} out of {} nodes",
+                        index, child, self.node_count
+                    )));
+                }
+                to_visit.push(child as usize);
+            }
+
+            let dimension = read_u64(self.records, offset + 16);
+            if dimension >= self.dims as u64 {
+                return Err(KdtreeError::InvalidFormat(format!(
+                    "node {} splits on dimension {} of {}",
+                    index, dimension, self.dims
+                )));
+            }
+        }
+
+        match visited.iter().position(|v| !v) {
+            Some(index) => Err(KdtreeError::InvalidFormat(format!(
+                "node {} is not reachable from the root",
+                index
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn node(&self, index: usize) -> NodeRecord {
+        let offset = index * record_len(self.dims);
+        let child = |at: usize| match read_u64(self.records, at) {
+            NO_CHILD => None,
+            child => Some(child as usize),
+        };
+
+        NodeRecord {
+            left_node: child(offset),
+            right_node: child(offset + 8),
+            dimension: read_u64(self.records, offset + 16) as usize,
+            split_on: read_f64(self.records, offset + 24),
+        }
+    }
+
+    fn read_coords(&self, index: usize, coords: &mut [f64]) {
+        let offset = index * record_len(self.dims) + NODE_FIXED_FIELDS * 8;
+        for (i, coord) in coords.iter_mut().enumerate() {
+            *coord = read_f64(self.records, offset + i * 8);
+        }
+    }
+
+    fn point(&self, index: usize) -> ViewPoint {
+        let mut dims = vec![0.; self.dims];
+        self.read_coords(index, &mut dims);
+
+        ViewPoint {
+            id: read_u64(self.records, index * record_len(self.dims) + 32),
+            dims,
+        }
+    }
+}
+
+fn record_len(dims: usize) -> usize {
+    (NODE_FIXED_FIELDS + dims) * 8
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_ne_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_ne_bytes(buf)
+}
+
+fn read_f64(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_bits(read_u64(bytes, offset))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::euclidean;
+    use crate::test_common::Point3WithId;
+    use rand::Rng;
+
+    fn random_points(count: usize) -> Vec<Point3WithId> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|i| {
+                Point3WithId::new(
+                    i as i32,
+                    rng.gen_range(-10., 10.),
+                    rng.gen_range(-10., 10.),
+                    rng.gen_range(-10., 10.),
+                )
+            })
+            .collect()
+    }
+
+    fn written(tree: &Kdtree<Point3WithId>) -> Vec<u8> {
+        let mut bytes = vec![];
+        tree.write_binary(&mut bytes, |p| p.id as u64).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn view_answers_same_as_tree() {
+        let points = random_points(500);
+        let tree = Kdtree::from_vec(points.clone()).unwrap();
+        let bytes = written(&tree);
+        let view = KdtreeView::new(&bytes).unwrap();
+
+        assert_eq!(3, view.dims());
+        assert_eq!(500, view.len());
+
+        for q in random_points(100) {
+            let expected = tree.nearest_search(&q);
+            let found = view.nearest_search(q.dims()).unwrap();
+            assert_eq!(expected.id as u64, found.id);
+            assert_eq!(expected.dims(), found.dims());
+
+            let expected_k = tree.nearest_k_search(&q, 5);
+            let found_k = view.nearest_k_search(q.dims(), 5).unwrap();
+            assert_eq!(
+                expected_k
+                    .iter()
+                    .map(|(p, d)| (p.id as u64, *d))
+                    .collect::<Vec<_>>(),
+                found_k.iter().map(|(p, d)| (p.id, *d)).collect::<Vec<_>>()
+            );
+
+            let mut expected_within = tree
+                .within(&q, 4., &euclidean)
+                .iter()
+                .map(|p| p.id as u64)
+                .collect::<Vec<_>>();
+            let mut found_within = view
+                .within(q.dims(), 4., &euclidean)
+                .unwrap()
+                .iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>();
+            expected_within.sort_unstable();
+            found_within.sort_unstable();
+            assert_eq!(expected_within, found_within);
+
+            let min = [q.dims()[0] - 3., q.dims()[1] - 3., q.dims()[2] - 3.];
+            let max = [q.dims()[0] + 3., q.dims()[1] + 3., q.dims()[2] + 3.];
+            let mut expected_box = tree
+                .within_box(&min, &max)
+                .iter()
+                .map(|p| p.id as u64)
+                .collect::<Vec<_>>();
+            let mut found_box = view
+                .within_box(&min, &max)
+                .unwrap()
+                .iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>();
+            expected_box.sort_unstable();
+            found_box.sort_unstable();
+            assert_eq!(expected_box, found_box);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let tree = Kdtree::new(&mut random_points(10)).unwrap();
+        let bytes = written(&tree);
+
+        match KdtreeView::new(&bytes[..bytes.len() - 1]) {
+            Err(KdtreeError::Truncated { expected, actual }) => {
+                assert_eq!(bytes.len(), expected);
+                assert_eq!(bytes.len() - 1, actual);
+            }
+            _ => panic!("expected truncation error"),
+        }
+        assert!(matches!(
+            KdtreeView::new(&bytes[..10]),
+            Err(KdtreeError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_headers() {
+        let tree = Kdtree::new(&mut random_points(10)).unwrap();
+        let bytes = written(&tree);
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            KdtreeView::new(&bad_magic),
+            Err(KdtreeError::BadMagic)
+        ));
+
+        let mut other_endianness = bytes.clone();
+        other_endianness[8..12].reverse();
+        assert!(matches!(
+            KdtreeView::new(&other_endianness),
+            Err(KdtreeError::EndiannessMismatch)
+        ));
+
+        let mut future_version = bytes.clone();
+        future_version[12..16].copy_from_slice(&2u32.to_ne_bytes());
+        assert!(matches!(
+            KdtreeView::new(&future_version),
+            Err(KdtreeError::UnsupportedVersion(2))
+        ));
+
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        assert!(matches!(
+            KdtreeView::new(&corrupted),
+            Err(KdtreeError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_cycles_even_with_valid_checksum() {
+        let mut points = vec![
+            Point3WithId::new(0, 0., 0., 0.),
+            Point3WithId::new(1, 1., 0., 0.),
+        ];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let mut bytes = written(&tree);
+
+        // point the child of the root back at the root
+        let root_children = &mut bytes[HEADER_LEN..HEADER_LEN + 16];
+        for child in root_children.chunks_mut(8) {
+            if u64::from_ne_bytes([
+                child[0], child[1], child[2], child[3], child[4], child[5], child[6], child[7],
+            ]) != NO_CHILD
+            {
+                child.copy_from_slice(&0u64.to_ne_bytes());
+            }
+        }
+        let checksum = fnv1a(&bytes[HEADER_LEN..]);
+        bytes[32..40].copy_from_slice(&checksum.to_ne_bytes());
+
+        assert!(matches!(
+            KdtreeView::new(&bytes),
+            Err(KdtreeError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn view_rejects_queries_of_another_dimension() {
+        let tree = Kdtree::from_vec(random_points(20)).unwrap();
+        let bytes = written(&tree);
+        let view = KdtreeView::new(&bytes).unwrap();
+
+        assert!(matches!(
+            view.nearest_search(&[0., 0.]),
+            Err(KdtreeError::DimensionMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(view.nearest_k_search(&[0.], 2).is_err());
+        assert!(view.within(&[0., 0., 0., 0.], 1., &euclidean).is_err());
+        assert!(view.within_box(&[0., 0., 0.], &[1., 1.]).is_err());
+        assert!(view.within_box(&[0., 0., 0.], &[1., 1., 1.]).is_ok());
+    }
+
+    #[test]
+    fn refuses_to_write_points_of_varying_dimension() {
+        let mut points = vec![vec![0., 0.], vec![1., 1., 1.]];
+        let tree = Kdtree::new(&mut points).unwrap();
+
+        let result = tree.write_binary(&mut vec![], |_| 0);
+        assert!(matches!(result, Err(KdtreeError::DimensionMismatch { .. })));
+    }
+}
@@ -1,7 +1,9 @@
 use crate::*;
 
+/// Axis aligned box with one `(min, max)` pair per dimension, tracking its widest dimension.
+#[derive(Clone)]
 pub struct Bounds {
-    pub bounds: [(f64, f64); 3],
+    pub bounds: Vec<(f64, f64)>,
 
     widest_dim: usize,
     midvalue_of_widest_dim: f64,
@@ -9,21 +11,22 @@ pub struct Bounds {
 
 impl Bounds {
     pub fn new_from_points<T: KdtreePointTrait>(points: &[T]) -> Bounds {
+        Bounds::new_from_dims(points.iter().map(|p| p.dims()))
+    }
+
+    pub fn new_from_dims<'a, I: IntoIterator<Item = &'a [f64]>>(dims: I) -> Bounds {
+        let mut dims = dims.into_iter();
+        let first = dims.next().expect("bounds need at least one point");
         let mut bounds = Bounds {
-            bounds: [(0., 0.), (0., 0.), (0., 0.)],
+            bounds: first.iter().map(|value| (*value, *value)).collect(),
             widest_dim: 0,
             midvalue_of_widest_dim: 0.,
         };
 
-        for i in 0..points[0].dims().len() {
-            bounds.bounds[i].0 = points[0].dims()[i];
-            bounds.bounds[i].1 = points[0].dims()[i];
-        }
-
-        for v in points.iter() {
-            for dim in 0..v.dims().len() {
-                bounds.bounds[dim].0 = bounds.bounds[dim].0.min(v.dims()[dim]);
-                bounds.bounds[dim].1 = bounds.bounds[dim].1.max(v.dims()[dim]);
+        for v in dims {
+            for (bound, value) in bounds.bounds.iter_mut().zip(v) {
+                bound.0 = bound.0.min(*value);
+                bound.1 = bound.1.max(*value);
             }
         }
 
@@ -41,29 +44,26 @@ impl Bounds {
     }
 
     pub fn clone_moving_max(&self, value: f64, dimension: usize) -> Bounds {
-        let mut cloned = Bounds {
-            bounds: self.bounds.clone(),
-            ..*self
-        };
-        cloned.bounds[dimension].1 = value;
-
-        cloned.calculate_variables();
+        let mut cloned = self.clone();
+        cloned.set(dimension, (cloned.bounds[dimension].0, value));
 
         cloned
     }
 
     pub fn clone_moving_min(&self, value: f64, dimension: usize) -> Bounds {
-        let mut cloned = Bounds {
-            bounds: self.bounds.clone(),
-            ..*self
-        };
-        cloned.bounds[dimension].0 = value;
-
-        cloned.calculate_variables();
+        let mut cloned = self.clone();
+        cloned.set(dimension, (value, cloned.bounds[dimension].1));
 
         cloned
     }
 
+    /// Replaces the extent of one dimension in place, cheaper than cloning when it is restored afterwards.
+    pub fn set(&mut self, dimension: usize, bound: (f64, f64)) {
+        self.bounds[dimension] = bound;
+
+        self.calculate_variables();
+    }
+
     fn calculate_widest_dim(&mut self) {
         let mut widest_dimension = 0usize;
         let mut max_found_spread = self.bounds[0].1 - self.bounds[0].0;
@@ -105,4 +105,15 @@ mod tests {
 
         assert_eq!(1, bounds.get_widest_dim());
     }
+
+    #[test]
+    fn bounds_have_as_many_dimensions_as_the_points() {
+        let a = [0., 0., 0., 0., 0.];
+        let b = [1., 2., 1., 0.5, 3.];
+        let bounds = Bounds::new_from_dims(vec![&a[..], &b[..]]);
+
+        assert_eq!(5, bounds.bounds.len());
+        assert_eq!(4, bounds.get_widest_dim());
+        assert_eq!(1.5, bounds.get_midvalue_of_widest_dim());
+    }
 }
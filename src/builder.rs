@@ -0,0 +1,157 @@
+//! Configuring how a tree is built.
+
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+use split::SplitRule;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Settings a tree was built with. They are stored on the tree, so rebuilds triggered by
+/// `insert_node`, `rebuild_tree` or `gather_points_and_rebuild` use them too.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TreeSettings {
+    pub split_rule: SplitRule,
+    /// `insert_node` rebuilds the tree once an insert goes this many times deeper
+    /// than the tree was right after its last rebuild.
+    pub rebuild_depth_factor: f64,
+    /// Store the nodes in the van Emde Boas layout after every rebuild, see `Kdtree::optimize_layout`.
+    pub optimize_layout: bool,
+}
+
+impl Default for TreeSettings {
+    fn default() -> TreeSettings {
+        TreeSettings {
+            split_rule: SplitRule::default(),
+            rebuild_depth_factor: 4.,
+            optimize_layout: false,
+        }
+    }
+}
+
+impl TreeSettings {
+    // A factor below 1 or NaN would rebuild on every insert or never, an infinite one never.
+    pub(crate) fn check(&self) -> Result<(), KdtreeError> {
+        let factor = self.rebuild_depth_factor;
+        if !factor.is_finite() || factor < 1. {
+            return Err(KdtreeError::InvalidSettings(format!(
+                "rebuild_depth_factor must be a finite number of at least 1, got {}",
+                factor
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds trees with non default settings, `Kdtree::new` is the shortcut for the defaults.
+///
+/// ```
+/// use kdtree::{KdtreeBuilder, SplitRule};
+///
+/// let mut points = vec![[0., 0.], [1., 5.], [2., 1.]];
+/// let tree = KdtreeBuilder::new()
+///     .split_rule(SplitRule::Median)
+///     .optimize_layout(true)
+///     .build(&mut points)
+///     .unwrap();
+/// assert_eq!([2., 1.], tree.nearest_search(&[3., 1.]));
+/// assert_eq!(SplitRule::Median, tree.settings().split_rule);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KdtreeBuilder {
+    settings: TreeSettings,
+}
+
+impl KdtreeBuilder {
+    pub fn new() -> KdtreeBuilder {
+        KdtreeBuilder::default()
+    }
+
+    pub fn split_rule(mut self, split_rule: SplitRule) -> KdtreeBuilder {
+        self.settings.split_rule = split_rule;
+        self
+    }
+
+    /// Lower factors keep the tree closer to balanced under inserts at the cost of more rebuilds.
+    /// `build` fails with `InvalidSettings` unless the factor is finite and at least 1.
+    pub fn rebuild_depth_factor(mut self, factor: f64) -> KdtreeBuilder {
+        self.settings.rebuild_depth_factor = factor;
+        self
+    }
+
+    pub fn optimize_layout(mut self, optimize_layout: bool) -> KdtreeBuilder {
+        self.settings.optimize_layout = optimize_layout;
+        self
+    }
+
+    /// Builds the tree, reordering `points`.
+    pub fn build<KdtreePoint: KdtreePointTrait>(
+        &self,
+        points: &mut [KdtreePoint],
+    ) -> Result<Kdtree<KdtreePoint>, KdtreeError> {
+        self.settings.check()?;
+        Kdtree::new_with_settings(points, self.settings).ok_or(KdtreeError::EmptyInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::Point2WithId;
+
+    #[test]
+    fn empty_input_is_an_error() {
+        match KdtreeBuilder::new().build::<Point2WithId>(&mut []) {
+            Err(KdtreeError::EmptyInput) => {}
+            other => panic!("unexpected {:?}", other.map(|t| t.len())),
+        }
+    }
+
+    #[test]
+    fn rebuild_depth_factor_must_be_at_least_one() {
+        for factor in &[0.5, -1., f64::NAN, f64::INFINITY] {
+            let built = KdtreeBuilder::new()
+                .rebuild_depth_factor(*factor)
+                .build(&mut [Point2WithId::new(0, 0., 0.)]);
+            assert!(matches!(built, Err(KdtreeError::InvalidSettings(_))));
+        }
+
+        assert!(KdtreeBuilder::new()
+            .rebuild_depth_factor(1.)
+            .build(&mut [Point2WithId::new(0, 0., 0.)])
+            .is_ok());
+    }
+
+    #[test]
+    fn rebuilds_keep_the_settings() {
+        let mut points: Vec<_> = (0..16)
+            .map(|i| Point2WithId::new(i, f64::from(i), 0.))
+            .collect();
+        let mut tree = KdtreeBuilder::new()
+            .split_rule(SplitRule::Median)
+            .rebuild_depth_factor(1.)
+            .optimize_layout(true)
+            .build(&mut points)
+            .unwrap();
+        let settings = *tree.settings();
+
+        // inserts in a row keep going deeper, a factor of 1 rebuilds long before the default 4 would.
+        for i in 0..8 {
+            tree.insert_node(Point2WithId::new(100 + i, 20. + f64::from(i), 0.));
+        }
+        assert!(tree.stats().inserts_since_last_rebuild < 8);
+        assert_eq!(settings, *tree.settings());
+
+        tree.gather_points_and_rebuild();
+        let mut expected = tree.clone();
+        expected.optimize_layout();
+        assert_eq!(
+            expected.iter().collect::<Vec<_>>(),
+            tree.iter().collect::<Vec<_>>()
+        );
+        assert!(tree.validate().is_ok());
+    }
+}
@@ -0,0 +1,136 @@
+//! Sharing a tree between reader threads while it is being modified.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use binary::KdtreeView;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+// Trees are only ever read through `&self`, make sure they stay shareable between threads.
+#[allow(dead_code)]
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[allow(dead_code)]
+fn assert_trees_are_send_sync<P: KdtreePointTrait + Send + Sync>() {
+    assert_send_sync::<Kdtree<P>>();
+    assert_send_sync::<KdtreeView>();
+    assert_send_sync::<ConcurrentKdtree<P>>();
+}
+
+/// Tree that can be queried from many threads while one writer modifies it.
+///
+/// Readers take a `snapshot` and query it for as long as they like. Taking one only holds a read
+/// lock while the `Arc` is cloned. Writers work on a copy of the current tree and swap the result
+/// in, so readers never see a half rebuilt tree and never wait for a rebuild. Snapshots taken
+/// before a swap keep the old tree alive.
+///
+/// The price of reads never waiting for a rebuild is that every write copies the whole tree,
+/// so a single `insert_node` is O(n). Group inserts with `insert_nodes` or `update` to pay for one copy.
+pub struct ConcurrentKdtree<KdtreePoint> {
+    current: RwLock<Arc<Kdtree<KdtreePoint>>>,
+    writer: Mutex<()>,
+}
+
+impl<KdtreePoint: KdtreePointTrait> ConcurrentKdtree<KdtreePoint> {
+    pub fn new(tree: Kdtree<KdtreePoint>) -> ConcurrentKdtree<KdtreePoint> {
+        ConcurrentKdtree {
+            current: RwLock::new(Arc::new(tree)),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the current tree, unaffected by later writes.
+    pub fn snapshot(&self) -> Arc<Kdtree<KdtreePoint>> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Swaps in `tree`, readers holding older snapshots keep them.
+    pub fn replace(&self, tree: Kdtree<KdtreePoint>) {
+        let _writer = self.writer.lock().unwrap();
+        self.swap(tree);
+    }
+
+    /// Builds a new tree from `points` with the current tree's settings and swaps it in.
+    /// Fails with `EmptyInput` and keeps the current tree if `points` is empty.
+    pub fn rebuild(&self, points: &mut [KdtreePoint]) -> Result<(), KdtreeError> {
+        let _writer = self.writer.lock().unwrap();
+        let settings = *self.snapshot().settings();
+        let tree = Kdtree::new_with_settings(points, settings).ok_or(KdtreeError::EmptyInput)?;
+        self.swap(tree);
+
+        Ok(())
+    }
+
+    /// Applies `modify` to a copy of the current tree and swaps the copy in.
+    /// Writers are serialized, so no update is lost.
+    pub fn update<F>(&self, modify: F)
+    where
+        F: FnOnce(&mut Kdtree<KdtreePoint>),
+    {
+        let _writer = self.writer.lock().unwrap();
+        let mut tree = (*self.snapshot()).clone();
+        modify(&mut tree);
+        self.swap(tree);
+    }
+
+    /// Copies the whole tree to insert one point, prefer `insert_nodes` for more than a few.
+    pub fn insert_node(&self, node_to_add: KdtreePoint) {
+        self.update(|tree| tree.insert_node(node_to_add));
+    }
+
+    /// Inserts all of `nodes_to_add` into a single copy of the tree.
+    pub fn insert_nodes<I>(&self, nodes_to_add: I)
+    where
+        I: IntoIterator<Item = KdtreePoint>,
+    {
+        self.update(|tree| {
+            for node in nodes_to_add {
+                tree.insert_node(node);
+            }
+        });
+    }
+
+    fn swap(&self, tree: Kdtree<KdtreePoint>) {
+        *self.current.write().unwrap() = Arc::new(tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::Point2WithId;
+
+    #[test]
+    fn snapshots_are_not_affected_by_writes() {
+        let tree = ConcurrentKdtree::new(Kdtree::new(&mut [Point2WithId::new(0, 0., 0.)]).unwrap());
+        let before = tree.snapshot();
+
+        tree.insert_node(Point2WithId::new(1, 5., 5.));
+        let query = Point2WithId::new(-1, 4., 4.);
+        assert_eq!(0, before.nearest_search(&query).id);
+        assert_eq!(1, tree.snapshot().nearest_search(&query).id);
+
+        tree.rebuild(&mut [Point2WithId::new(2, 3., 3.)]).unwrap();
+        assert_eq!(2, tree.snapshot().nearest_search(&query).id);
+    }
+
+    #[test]
+    fn batched_inserts_and_empty_rebuilds() {
+        let tree = ConcurrentKdtree::new(Kdtree::new(&mut [Point2WithId::new(0, 0., 0.)]).unwrap());
+
+        tree.insert_nodes((1..20).map(|i| Point2WithId::new(i, f64::from(i), 0.)));
+        assert_eq!(20, tree.snapshot().iter().count());
+        assert_eq!(
+            7,
+            tree.snapshot()
+                .nearest_search(&Point2WithId::new(-1, 7.2, 0.))
+                .id
+        );
+
+        assert!(matches!(
+            tree.rebuild(&mut []),
+            Err(KdtreeError::EmptyInput)
+        ));
+        assert_eq!(20, tree.snapshot().iter().count());
+    }
+}
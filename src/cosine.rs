@@ -0,0 +1,174 @@
+//! Lookups by cosine similarity, e.g. for embedding vectors.
+//!
+//! Points and queries are normalised onto the unit sphere, where the squared euclidean distance
+//! between two points is `2 - 2 * cos`. The most similar points are then the euclidean nearest
+//! ones, found with the regular tree search, and distances are turned back into similarities.
+
+use std::cmp::Ordering;
+
+use distance::squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+/// Point stored by a `CosineTree`, `point` is the original, not normalised, point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedPoint<P> {
+    dims: Vec<f64>,
+    pub point: P,
+}
+
+impl<P: Clone + PartialEq> KdtreePointTrait for NormalizedPoint<P> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+/// Cosine similarity of two vectors, `None` if either is the zero vector.
+pub fn cosine_similarity(a: &[f64], b: &[f64]) -> Option<f64> {
+    let (a, b) = (normalize(a)?, normalize(b)?);
+
+    Some(similarity(squared_euclidean(&a, &b)))
+}
+
+fn normalize(dims: &[f64]) -> Option<Vec<f64>> {
+    let norm = dims.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm == 0. || !norm.is_finite() {
+        return None;
+    }
+
+    Some(dims.iter().map(|x| x / norm).collect())
+}
+
+fn similarity(squared_chord: f64) -> f64 {
+    (1. - squared_chord / 2.).clamp(-1., 1.)
+}
+
+/// Tree answering queries by cosine similarity, most similar first.
+pub struct CosineTree<P> {
+    tree: Kdtree<NormalizedPoint<P>>,
+}
+
+impl<P: KdtreePointTrait> CosineTree<P> {
+    /// Fails with `InvalidMetric` if a point is the zero vector, which has no direction.
+    pub fn new(points: Vec<P>) -> Result<CosineTree<P>, KdtreeError> {
+        let normalized = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let dims = normalize(point.dims()).ok_or_else(|| {
+                    KdtreeError::InvalidMetric(format!(
+                        "point {} has no direction to compare",
+                        index
+                    ))
+                })?;
+                Ok(NormalizedPoint { dims, point })
+            })
+            .collect::<Result<Vec<_>, KdtreeError>>()?;
+        let tree = Kdtree::from_vec(normalized).ok_or(KdtreeError::EmptyInput)?;
+
+        Ok(CosineTree { tree })
+    }
+
+    /// Underlying tree of normalised points.
+    pub fn tree(&self) -> &Kdtree<NormalizedPoint<P>> {
+        &self.tree
+    }
+
+    /// Up to `k` points most similar to `query` with their cosine similarities, most similar first.
+    /// Empty for the zero vector.
+    pub fn most_similar(&self, query: &P, k: usize) -> Vec<(&P, f64)> {
+        let query = match normalize(query.dims()) {
+            Some(query) => query,
+            None => return vec![],
+        };
+
+        self.tree
+            .nearest_k_search_by_dims(&query, k)
+            .into_iter()
+            .map(|(p, squared_chord)| (&p.point, similarity(squared_chord)))
+            .collect()
+    }
+
+    /// Points with a cosine similarity to `query` of at least `min_similarity`, most similar first.
+    /// Empty for the zero vector.
+    pub fn at_least_similar(&self, query: &P, min_similarity: f64) -> Vec<(&P, f64)> {
+        let query = match normalize(query.dims()) {
+            Some(dims) => NormalizedPoint {
+                dims,
+                point: query.clone(),
+            },
+            None => return vec![],
+        };
+
+        let max_squared_chord = 2. - 2. * min_similarity;
+        let mut found: Vec<_> = self
+            .tree
+            .within(&query, max_squared_chord, &squared_euclidean)
+            .into_iter()
+            .map(|p| {
+                let similarity = similarity(squared_euclidean(query.dims(), p.dims()));
+                (&p.point, similarity)
+            })
+            .collect();
+        found.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embeddings() -> Vec<Vec<f64>> {
+        (0..60)
+            .map(|i| {
+                (0..6)
+                    .map(|d| f64::from((i * (d + 3) * 7 + d * 11) % 13) - 6.5)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_results_as_linear_search_in_six_dimensions() {
+        let points = embeddings();
+        let tree = CosineTree::new(points.clone()).unwrap();
+
+        for query in points
+            .iter()
+            .take(10)
+            .map(|q| q.iter().map(|x| x * 0.5 + 1.).collect::<Vec<_>>())
+        {
+            let mut linear: Vec<f64> = points
+                .iter()
+                .map(|p| cosine_similarity(&query, p).unwrap())
+                .collect();
+            linear.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+            let top: Vec<f64> = tree.most_similar(&query, 5).iter().map(|r| r.1).collect();
+            for (expected, actual) in linear.iter().zip(&top) {
+                assert!((expected - actual).abs() < 1e-9);
+            }
+
+            let similar = tree.at_least_similar(&query, 0.5);
+            assert_eq!(linear.iter().filter(|s| **s >= 0.5).count(), similar.len());
+            assert!(similar.windows(2).all(|w| w[0].1 >= w[1].1));
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_length() {
+        let tree = CosineTree::new(vec![vec![1., 0.], vec![0., 5.], vec![-3., -3.]]).unwrap();
+
+        let found = tree.most_similar(&vec![0., 0.1], 1);
+        assert_eq!(vec![0., 5.], *found[0].0);
+        assert!((1. - found[0].1).abs() < 1e-12);
+        assert!(tree.most_similar(&vec![0., 0.], 1).is_empty());
+
+        match CosineTree::new(vec![vec![1., 0.], vec![0., 0.]]) {
+            Err(KdtreeError::InvalidMetric(_)) => {}
+            _ => panic!("accepted the zero vector"),
+        }
+    }
+}
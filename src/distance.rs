@@ -1,15 +1,112 @@
+//! Distance functions usable with `Kdtree::within` and friends.
+//!
+//! On x86_64 the squared euclidean distance of 2, 3, 4 and 8 dimensional points uses SIMD kernels,
+//! AVX ones when the CPU has it. Other dimension counts and targets use plain scalar code.
+//! Kernels may add the coordinates up in a different order, so results can differ from the scalar
+//! code in the last bits.
+
+use kdtree::KdtreePointTrait;
+#[cfg(target_arch = "x86_64")]
+use simd;
+
 pub fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+    squared_euclidean(a, b).sqrt()
+}
+
+#[inline]
+pub fn squared_euclidean(a: &[f64], b: &[f64]) -> f64 {
     debug_assert!(a.len() == b.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if a.len() == b.len() {
+            if let Some(kernel) = simd::f64_kernel(a.len()) {
+                return kernel(a, b);
+            }
+        }
+    }
+
+    scalar_squared_euclidean(a, b)
+}
+
+pub fn euclidean_f32(a: &[f32], b: &[f32]) -> f32 {
+    squared_euclidean_f32(a, b).sqrt()
+}
+
+#[inline]
+pub fn squared_euclidean_f32(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert!(a.len() == b.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if a.len() == b.len() {
+            if let Some(kernel) = simd::f32_kernel(a.len()) {
+                return kernel(a, b);
+            }
+        }
+    }
+
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Squared euclidean distance with each dimension's squared difference scaled by `weights[i]`.
+pub fn weighted_squared_euclidean(a: &[f64], b: &[f64], weights: &[f64]) -> f64 {
+    debug_assert!(a.len() == b.len() && a.len() == weights.len());
+
     a.iter()
         .zip(b.iter())
-        .map(|(x1, x2)| (*x1 - *x2).powi(2))
-        .fold(0f64, |acc, add| acc + add)
-        .sqrt()
+        .zip(weights.iter())
+        .map(|((x, y), w)| w * (x - y) * (x - y))
+        .sum()
 }
 
-pub fn squared_euclidean(a: &[f64], b: &[f64]) -> f64 {
-    debug_assert!(a.len() == b.len());
+/// Squared euclidean distance with coordinates wrapping around every `periods[i]` along dimension `i`,
+/// measured between the closest images of `a` and `b`. A period of `0.` leaves that dimension unwrapped.
+pub fn periodic_squared_euclidean(a: &[f64], b: &[f64], periods: &[f64]) -> f64 {
+    debug_assert!(a.len() == b.len() && a.len() == periods.len());
+
+    a.iter()
+        .zip(b.iter())
+        .zip(periods.iter())
+        .map(|((x, y), period)| {
+            let mut d = (x - y).abs();
+            if *period > 0. {
+                d %= period;
+                d = d.min(period - d);
+            }
+            d * d
+        })
+        .sum()
+}
 
+/// Squared euclidean distances from `query` to each of `points`, in order.
+/// The kernel is picked once for the whole batch, which makes this cheaper than calling
+/// `squared_euclidean` in a loop for linear scans.
+pub fn squared_euclidean_many<P: KdtreePointTrait>(query: &[f64], points: &[P]) -> Vec<f64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if let Some(kernel) = simd::f64_kernel(query.len()) {
+            return points
+                .iter()
+                .map(|p| {
+                    let dims = p.dims();
+                    if dims.len() == query.len() {
+                        kernel(query, dims)
+                    } else {
+                        scalar_squared_euclidean(query, dims)
+                    }
+                })
+                .collect();
+        }
+    }
+
+    points
+        .iter()
+        .map(|p| scalar_squared_euclidean(query, p.dims()))
+        .collect()
+}
+
+fn scalar_squared_euclidean(a: &[f64], b: &[f64]) -> f64 {
     a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
 }
 
@@ -42,4 +139,55 @@ mod tests {
 
         assert_eq!(8., squared_euclidean(&a, &c));
     }
+
+    #[test]
+    fn kernels_match_scalar_code() {
+        let a: Vec<f64> = (0..8).map(|i| f64::from(i) * 1.5 - 3.).collect();
+        let b: Vec<f64> = (0..8).map(|i| f64::from(i * i) * 0.25).collect();
+
+        for dims in 1..=8 {
+            let (a, b) = (&a[..dims], &b[..dims]);
+            let expected = scalar_squared_euclidean(a, b);
+            assert!((expected - squared_euclidean(a, b)).abs() < 1e-9);
+            assert!((expected.sqrt() - euclidean(a, b)).abs() < 1e-9);
+
+            let a32: Vec<f32> = a.iter().map(|x| *x as f32).collect();
+            let b32: Vec<f32> = b.iter().map(|x| *x as f32).collect();
+            assert!((expected as f32 - squared_euclidean_f32(&a32, &b32)).abs() < 1e-3);
+
+            // one of the two starts is not 8 byte aligned, the kernels must not assume it is.
+            let padded: Vec<f32> = Some(0.).into_iter().chain(a32.iter().cloned()).collect();
+            assert_eq!(
+                squared_euclidean_f32(&a32, &b32),
+                squared_euclidean_f32(&padded[1..], &b32)
+            );
+        }
+    }
+
+    #[test]
+    fn many_matches_single_distances() {
+        use test_common::{Point1WithId, Point2WithId, Point3WithId};
+
+        let points: Vec<_> = (0..10)
+            .map(|i| Point3WithId::new(i, f64::from(i), 2., f64::from(-i)))
+            .collect();
+        let query = [1., 1., 1.];
+        let expected: Vec<f64> = points
+            .iter()
+            .map(|p| squared_euclidean(&query, p.dims()))
+            .collect();
+        assert_eq!(expected, squared_euclidean_many(&query, &points));
+
+        assert_eq!(
+            vec![1., 4.],
+            squared_euclidean_many(
+                &[0., 1.],
+                &[Point2WithId::new(0, 1., 1.), Point2WithId::new(1, 0., 3.)]
+            )
+        );
+        assert_eq!(
+            vec![9.],
+            squared_euclidean_many(&[0.], &[Point1WithId::new(0, 3.)])
+        );
+    }
 }
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum KdtreeError {
+    Io(io::Error),
+    /// There were no points to build a tree from.
+    EmptyInput,
+    /// Input does not start with the expected file signature.
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// File was written on a machine with different byte order.
+    EndiannessMismatch,
+    /// Input is shorter than its header says it should be.
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    ChecksumMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    /// Points do not all have the dimension count the operation requires.
+    DimensionMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// Input is structurally broken in a way not covered by the other variants.
+    InvalidFormat(String),
+    /// Tree structure breaks an invariant, reported by `Kdtree::validate`.
+    InvalidTree {
+        node: usize,
+        message: String,
+    },
+    /// Metric parameters, such as a covariance matrix, can not define a distance.
+    InvalidMetric(String),
+    /// Tree settings are out of range, such as a `rebuild_depth_factor` below 1.
+    InvalidSettings(String),
+    /// Text input could not be parsed, `line` is 1-based.
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for KdtreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KdtreeError::Io(e) => write!(f, "i/o error: {}", e),
+            KdtreeError::EmptyInput => write!(f, "no points to build the tree from"),
+            KdtreeError::BadMagic => write!(f, "not a kdtree file, bad magic bytes"),
+            KdtreeError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            KdtreeError::EndiannessMismatch => {
+                write!(f, "file was written with a different byte order")
+            }
+            KdtreeError::Truncated { expected, actual } => write!(
+                f,
+                "input truncated, expected {} bytes but got {}",
+                expected, actual
+            ),
+            KdtreeError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch, expected {:#018x} but computed {:#018x}",
+                expected, actual
+            ),
+            KdtreeError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "dimension mismatch, expected {} dimensions but got {}",
+                expected, actual
+            ),
+            KdtreeError::InvalidFormat(reason) => write!(f, "invalid format: {}", reason),
+            KdtreeError::InvalidTree { node, message } => {
+                write!(f, "invalid tree at node {}: {}", node, message)
+            }
+            KdtreeError::InvalidMetric(reason) => write!(f, "invalid metric: {}", reason),
+            KdtreeError::InvalidSettings(reason) => write!(f, "invalid settings: {}", reason),
+            KdtreeError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for KdtreeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KdtreeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for KdtreeError {
+    fn from(e: io::Error) -> KdtreeError {
+        KdtreeError::Io(e)
+    }
+}
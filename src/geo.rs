@@ -0,0 +1,198 @@
+//! Queries on latitude/longitude points with great-circle distances in metres.
+//!
+//! Points are stored as 3 dimensional coordinates on the unit sphere, where the straight line
+//! (chord) distance between two points grows monotonically with their great-circle distance.
+//! The regular euclidean tree search then gives exact answers near the poles and across the
+//! antimeridian, and chord lengths are converted to metres on the way out.
+
+use std::cmp::Ordering;
+
+use distance::squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+/// Mean earth radius in metres.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Latitude/longitude point in degrees, `id` is free for the caller to refer to its own data.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GeoPoint {
+    dims: [f64; 3],
+    pub lat: f64,
+    pub lon: f64,
+    pub id: usize,
+}
+
+impl GeoPoint {
+    pub fn new(id: usize, lat: f64, lon: f64) -> GeoPoint {
+        let (lat_r, lon_r) = (lat.to_radians(), lon.to_radians());
+        GeoPoint {
+            dims: [
+                lat_r.cos() * lon_r.cos(),
+                lat_r.cos() * lon_r.sin(),
+                lat_r.sin(),
+            ],
+            lat,
+            lon,
+            id,
+        }
+    }
+}
+
+impl KdtreePointTrait for GeoPoint {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+/// Great-circle distance in metres between two latitude/longitude points given in degrees.
+pub fn haversine(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let half_dlat = (lat2 - lat1) / 2.;
+    let half_dlon = (lon2 - lon1).to_radians() / 2.;
+
+    let a = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+    2. * EARTH_RADIUS * a.sqrt().min(1.).asin()
+}
+
+fn chord_to_metres(squared_chord: f64) -> f64 {
+    2. * EARTH_RADIUS * (squared_chord.sqrt() / 2.).min(1.).asin()
+}
+
+fn metres_to_squared_chord(metres: f64) -> f64 {
+    let angle = (metres / EARTH_RADIUS).min(std::f64::consts::PI);
+    let chord = 2. * (angle / 2.).sin();
+    chord * chord
+}
+
+/// Tree over `GeoPoint`s answering queries in metres.
+pub struct GeoTree {
+    tree: Kdtree<GeoPoint>,
+}
+
+impl GeoTree {
+    pub fn new(points: Vec<GeoPoint>) -> Result<GeoTree, KdtreeError> {
+        let tree = Kdtree::from_vec(points).ok_or(KdtreeError::EmptyInput)?;
+
+        Ok(GeoTree { tree })
+    }
+
+    /// Underlying tree of unit sphere coordinates.
+    pub fn tree(&self) -> &Kdtree<GeoPoint> {
+        &self.tree
+    }
+
+    pub fn insert_node(&mut self, point: GeoPoint) {
+        self.tree.insert_node(point);
+    }
+
+    /// Closest point to `lat`/`lon` and its distance in metres.
+    pub fn nearest(&self, lat: f64, lon: f64) -> (GeoPoint, f64) {
+        let query = GeoPoint::new(0, lat, lon);
+        let nearest = self.tree.nearest_search(&query);
+        let distance = chord_to_metres(squared_euclidean(query.dims(), nearest.dims()));
+
+        (nearest, distance)
+    }
+
+    /// Up to `k` closest points to `lat`/`lon` with their distances in metres, nearest first.
+    pub fn nearest_k(&self, lat: f64, lon: f64, k: usize) -> Vec<(&GeoPoint, f64)> {
+        let query = GeoPoint::new(0, lat, lon);
+
+        self.tree
+            .nearest_k_search(&query, k)
+            .into_iter()
+            .map(|(p, squared_chord)| (p, chord_to_metres(squared_chord)))
+            .collect()
+    }
+
+    /// Points within `radius` metres of `lat`/`lon` with their distances in metres, nearest first.
+    /// Empty for a negative or NaN radius.
+    pub fn within(&self, lat: f64, lon: f64, radius: f64) -> Vec<(&GeoPoint, f64)> {
+        // a negative radius would square to a positive chord.
+        if radius.is_nan() || radius < 0. {
+            return vec![];
+        }
+        let query = GeoPoint::new(0, lat, lon);
+
+        let mut found: Vec<_> = self
+            .tree
+            .within(&query, metres_to_squared_chord(radius), &squared_euclidean)
+            .into_iter()
+            .map(|p| {
+                (
+                    p,
+                    chord_to_metres(squared_euclidean(query.dims(), p.dims())),
+                )
+            })
+            .collect();
+        found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cities() -> Vec<GeoPoint> {
+        vec![
+            GeoPoint::new(0, 51.5074, -0.1278),   // London
+            GeoPoint::new(1, 48.8566, 2.3522),    // Paris
+            GeoPoint::new(2, -36.8485, 174.7633), // Auckland
+            GeoPoint::new(3, -17.7134, 178.0650), // Fiji
+            GeoPoint::new(4, 64.1466, -21.9426),  // Reykjavik
+            GeoPoint::new(5, 89.9, 0.),           // near the north pole
+            GeoPoint::new(6, 89.9, 180.),         // near the north pole, other side
+        ]
+    }
+
+    #[test]
+    fn haversine_matches_known_distances() {
+        // London - Paris is about 343.5 km.
+        let d = haversine(51.5074, -0.1278, 48.8566, 2.3522);
+        assert!((d - 343_500.).abs() < 1_000.);
+        assert_eq!(0., haversine(10., 20., 10., 20.));
+    }
+
+    #[test]
+    fn finds_neighbours_across_the_antimeridian_and_poles() {
+        let tree = GeoTree::new(cities()).unwrap();
+
+        // just east of the antimeridian, Fiji is closer than any degree based distance suggests.
+        let (nearest, metres) = tree.nearest(-17.7, -179.9);
+        assert_eq!(3, nearest.id);
+        assert!((metres - haversine(-17.7, -179.9, -17.7134, 178.0650)).abs() < 1.);
+
+        let near_pole = tree.within(89.95, 90., 20_000.);
+        let ids: Vec<usize> = near_pole.iter().map(|(p, _)| p.id).collect();
+        assert_eq!(2, ids.len());
+        assert!(ids.contains(&5) && ids.contains(&6));
+    }
+
+    #[test]
+    fn distances_are_in_metres_and_sorted() {
+        let tree = GeoTree::new(cities()).unwrap();
+
+        let found = tree.within(51.5074, -0.1278, 400_000.);
+        assert_eq!(
+            vec![0, 1],
+            found.iter().map(|(p, _)| p.id).collect::<Vec<_>>()
+        );
+        assert!(found[0].1 < 1e-3);
+        assert!((found[1].1 - haversine(51.5074, -0.1278, 48.8566, 2.3522)).abs() < 1.);
+        assert!(tree.within(51.5074, -0.1278, -400_000.).is_empty());
+        assert!(tree.within(51.5074, -0.1278, f64::NAN).is_empty());
+        assert_eq!(1, tree.within(51.5074, -0.1278, 0.).len());
+
+        let k = tree.nearest_k(51.5074, -0.1278, 3);
+        assert_eq!(
+            vec![0, 1, 4],
+            k.iter().map(|(p, _)| p.id).collect::<Vec<_>>()
+        );
+        for (p, metres) in k {
+            assert!((metres - haversine(51.5074, -0.1278, p.lat, p.lon)).abs() < 1.);
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! k nearest neighbour graph over all the points of a tree.
+//!
+//! Points are searched in preorder, each from its own node upwards rather than down from the root.
+//! The search starts with the parent and the parent's neighbours, which are close by and already
+//! known, and climbs only until the ball holding the neighbours found so far lies inside the cell of
+//! the current subtree, so the upper levels of the tree are rarely visited at all.
+
+use distance::squared_euclidean;
+use join::{children, SubtreeBoxes};
+use kdtree::{Kdtree, KdtreePointTrait};
+
+/// Neighbours of every stored point in compressed sparse row form, returned by `Kdtree::knn_graph`.
+///
+/// Points are numbered in storage order, the order of `Kdtree::iter`. The neighbours of point `i`
+/// are `neighbors[offsets[i]..offsets[i + 1]]`, nearest first, with their squared euclidean distances
+/// at the same positions of `squared_distances`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnnGraph {
+    pub offsets: Vec<usize>,
+    pub neighbors: Vec<usize>,
+    pub squared_distances: Vec<f64>,
+}
+
+impl KnnGraph {
+    /// Number of points in the graph.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn neighbors_of(&self, point: usize) -> &[usize] {
+        &self.neighbors[self.offsets[point]..self.offsets[point + 1]]
+    }
+
+    pub fn squared_distances_of(&self, point: usize) -> &[f64] {
+        &self.squared_distances[self.offsets[point]..self.offsets[point + 1]]
+    }
+}
+
+impl KnnGraph {
+    fn from_rows(rows: Vec<Vec<(usize, f64)>>, k: usize) -> KnnGraph {
+        let mut graph = KnnGraph {
+            offsets: Vec::with_capacity(rows.len() + 1),
+            neighbors: Vec::with_capacity(rows.len() * k),
+            squared_distances: Vec::with_capacity(rows.len() * k),
+        };
+        graph.offsets.push(0);
+        for row in rows {
+            for (neighbor, distance) in row {
+                graph.neighbors.push(neighbor);
+                graph.squared_distances.push(distance);
+            }
+            graph.offsets.push(graph.neighbors.len());
+        }
+
+        graph
+    }
+}
+
+struct AllNearest<'a, P: 'a> {
+    tree: &'a Kdtree<P>,
+    boxes: SubtreeBoxes,
+    k: usize,
+    parents: Vec<Option<usize>>,
+    // region of space each node's subtree covers, from the splits above it.
+    cell_min: Vec<f64>,
+    cell_max: Vec<f64>,
+}
+
+impl<'a, P: KdtreePointTrait> AllNearest<'a, P> {
+    fn new(tree: &'a Kdtree<P>, k: usize) -> AllNearest<'a, P> {
+        let count = tree.nodes.len();
+        let dimensions = tree.nodes[0].point.dims().len();
+        let mut all = AllNearest {
+            tree,
+            boxes: SubtreeBoxes::new(tree),
+            k,
+            parents: vec![None; count],
+            cell_min: vec![f64::NEG_INFINITY; count * dimensions],
+            cell_max: vec![f64::INFINITY; count * dimensions],
+        };
+        all.cells(0, dimensions);
+
+        all
+    }
+
+    fn cells(&mut self, index: usize, dimensions: usize) {
+        let node = &self.tree.nodes[index];
+        for (child, is_left) in node
+            .left_node
+            .iter()
+            .map(|child| (*child, true))
+            .chain(node.right_node.iter().map(|child| (*child, false)))
+        {
+            self.parents[child] = Some(index);
+            for i in 0..dimensions {
+                self.cell_min[child * dimensions + i] = self.cell_min[index * dimensions + i];
+                self.cell_max[child * dimensions + i] = self.cell_max[index * dimensions + i];
+            }
+            if is_left {
+                self.cell_max[child * dimensions + node.dimension] = node.split_on;
+            } else {
+                self.cell_min[child * dimensions + node.dimension] = node.split_on;
+            }
+            self.cells(child, dimensions);
+        }
+    }
+
+    // Appends the rows of the subtree at `index`, searched in preorder so that
+    // `parent_row`, the neighbours of the parent, is known when a point is searched.
+    fn subtree_rows(
+        &self,
+        index: usize,
+        parent_row: &[(usize, f64)],
+        rows: &mut Vec<(usize, Vec<(usize, f64)>)>,
+    ) {
+        let row = self.nearest_others(index, parent_row);
+        for child in children(self.tree, index) {
+            self.subtree_rows(child, &row, rows);
+        }
+        rows.push((index, row));
+    }
+
+    fn nearest_others(&self, index: usize, parent_row: &[(usize, f64)]) -> Vec<(usize, f64)> {
+        let point = self.tree.nodes[index].point.dims();
+        let mut nearest = Vec::with_capacity(self.k + 1);
+
+        // the parent and its neighbours are close by and give a small bound from the start.
+        if let Some(parent) = self.parents[index] {
+            self.consider(point, index, parent, &mut nearest);
+            for &(neighbor, _) in parent_row {
+                self.consider(point, index, neighbor, &mut nearest);
+            }
+        }
+
+        for child in children(self.tree, index) {
+            self.search(point, index, child, &mut nearest);
+        }
+
+        // climb until the ball around the point holding its neighbours lies inside the subtree's cell.
+        let mut current = index;
+        while let Some(parent) = self.parents[current] {
+            if self.ball_inside_cell(point, current, &nearest) {
+                break;
+            }
+            self.consider(point, index, parent, &mut nearest);
+            for sibling in children(self.tree, parent).filter(|&child| child != current) {
+                self.search(point, index, sibling, &mut nearest);
+            }
+            current = parent;
+        }
+
+        nearest
+    }
+
+    fn bound(&self, nearest: &[(usize, f64)]) -> f64 {
+        if nearest.len() < self.k {
+            f64::INFINITY
+        } else {
+            nearest[self.k - 1].1
+        }
+    }
+
+    fn ball_inside_cell(&self, point: &[f64], index: usize, nearest: &[(usize, f64)]) -> bool {
+        let bound = self.bound(nearest);
+        let d = point.len();
+        point.iter().enumerate().all(|(i, x)| {
+            let gap = (x - self.cell_min[index * d + i]).min(self.cell_max[index * d + i] - x);
+            gap >= 0. && gap * gap >= bound
+        })
+    }
+
+    fn search(
+        &self,
+        point: &[f64],
+        index: usize,
+        searched: usize,
+        nearest: &mut Vec<(usize, f64)>,
+    ) {
+        if self.boxes.squared_distance_to_point(searched, point) > self.bound(nearest) {
+            return;
+        }
+
+        self.consider(point, index, searched, nearest);
+        let node = &self.tree.nodes[searched];
+        let (closer, farther) = if point[node.dimension] <= node.split_on {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+        for child in closer.into_iter().chain(farther) {
+            self.search(point, index, child, nearest);
+        }
+    }
+
+    fn consider(
+        &self,
+        point: &[f64],
+        index: usize,
+        candidate: usize,
+        nearest: &mut Vec<(usize, f64)>,
+    ) {
+        if candidate == index {
+            return;
+        }
+
+        let distance = squared_euclidean(point, self.tree.nodes[candidate].point.dims());
+        if nearest.len() < self.k || distance < nearest[nearest.len() - 1].1 {
+            // seeded candidates can be met again by the search.
+            if nearest.iter().any(|&(found, _)| found == candidate) {
+                return;
+            }
+            let insert_at = nearest
+                .iter()
+                .position(|&(_, d)| distance < d)
+                .unwrap_or(nearest.len());
+            nearest.insert(insert_at, (candidate, distance));
+            nearest.truncate(self.k);
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, P: KdtreePointTrait + Send + Sync> AllNearest<'a, P> {
+    // `subtree_rows` with the two subtrees below each node searched in parallel,
+    // down to `parallel_levels` levels below `index`.
+    fn par_subtree_rows(
+        &self,
+        index: usize,
+        parent_row: &[(usize, f64)],
+        parallel_levels: usize,
+    ) -> Vec<(usize, Vec<(usize, f64)>)> {
+        let row = self.nearest_others(index, parent_row);
+        let below = |child: Option<usize>| {
+            let mut rows = vec![];
+            if let Some(child) = child {
+                if parallel_levels == 0 {
+                    self.subtree_rows(child, &row, &mut rows);
+                } else {
+                    rows = self.par_subtree_rows(child, &row, parallel_levels - 1);
+                }
+            }
+            rows
+        };
+
+        let node = &self.tree.nodes[index];
+        let (mut rows, right) = rayon::join(|| below(node.left_node), || below(node.right_node));
+        rows.extend(right);
+        rows.push((index, row));
+
+        rows
+    }
+}
+
+// Rows indexed by point from `(point, row)` pairs covering every point once.
+fn by_point(count: usize, rows: Vec<(usize, Vec<(usize, f64)>)>) -> Vec<Vec<(usize, f64)>> {
+    let mut by_point = vec![vec![]; count];
+    for (index, row) in rows {
+        by_point[index] = row;
+    }
+
+    by_point
+}
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// The `k` nearest other points of every stored point, fewer if the tree is smaller.
+    /// Other points equal to a point count as its neighbours at distance 0.
+    pub fn knn_graph(&self, k: usize) -> KnnGraph {
+        let k = k.min(self.nodes.len() - 1);
+        if k == 0 {
+            return KnnGraph::from_rows(vec![vec![]; self.nodes.len()], k);
+        }
+
+        let mut rows = Vec::with_capacity(self.nodes.len());
+        AllNearest::new(self, k).subtree_rows(0, &[], &mut rows);
+
+        KnnGraph::from_rows(by_point(self.nodes.len(), rows), k)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<KdtreePoint: KdtreePointTrait + Send + Sync> Kdtree<KdtreePoint> {
+    /// `knn_graph` with the upward searches of different subtrees run in parallel.
+    /// Gives the same neighbours, equally distant ones may come in another order.
+    pub fn par_knn_graph(&self, k: usize) -> KnnGraph {
+        let k = k.min(self.nodes.len() - 1);
+        if k == 0 {
+            return KnnGraph::from_rows(vec![vec![]; self.nodes.len()], k);
+        }
+
+        // 2^8 subtrees are plenty of tasks to keep every thread busy.
+        let rows = AllNearest::new(self, k).par_subtree_rows(0, &[], 8);
+
+        KnnGraph::from_rows(by_point(self.nodes.len(), rows), k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::squared_euclidean;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::Point2WithId;
+
+    #[test]
+    fn neighbours_match_linear_search() {
+        let mut points: Vec<_> = (0..80)
+            .map(|i| Point2WithId::new(i, f64::from((i * 37) % 83), f64::from((i * 59) % 79)))
+            .collect();
+        points.push(Point2WithId::new(80, 0., 0.));
+        let mut tree = Kdtree::from_vec(points).unwrap();
+        tree.insert_node(Point2WithId::new(81, 0.5, 0.5));
+
+        let graph = tree.knn_graph(4);
+        let stored: Vec<_> = tree.iter().collect();
+        assert_eq!(stored.len(), graph.len());
+        assert_eq!(4 * stored.len(), graph.neighbors.len());
+
+        for (i, p) in stored.iter().enumerate() {
+            assert!(!graph.neighbors_of(i).contains(&i));
+
+            let mut linear: Vec<f64> = stored
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, q)| squared_euclidean(p.dims(), q.dims()))
+                .collect();
+            linear.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(&linear[..4], graph.squared_distances_of(i));
+
+            for (&j, &d) in graph
+                .neighbors_of(i)
+                .iter()
+                .zip(graph.squared_distances_of(i))
+            {
+                assert_eq!(d, squared_euclidean(p.dims(), stored[j].dims()));
+            }
+        }
+    }
+
+    #[test]
+    fn small_trees_and_duplicates() {
+        let tree = Kdtree::from_vec(vec![[1., 1.], [1., 1.], [4., 5.]]).unwrap();
+
+        let graph = tree.knn_graph(5);
+        assert_eq!(vec![0, 2, 4, 6], graph.offsets);
+        for i in 0..3 {
+            assert!(!graph.neighbors_of(i).contains(&i));
+        }
+        assert_eq!(0., graph.squared_distances_of(0)[0]);
+        assert_eq!(vec![25., 25.], graph.squared_distances_of(2));
+
+        assert!(tree.knn_graph(0).neighbors.is_empty());
+        for &k in &[usize::MAX, 1 << 61] {
+            assert_eq!(graph, tree.knn_graph(k));
+        }
+    }
+
+    #[test]
+    fn many_equal_points() {
+        let mut points = vec![[2., 2.]; 12];
+        points.push([0., 0.]);
+        let tree = Kdtree::from_vec(points).unwrap();
+
+        let graph = tree.knn_graph(3);
+        for i in 0..graph.len() {
+            let mut neighbors = graph.neighbors_of(i).to_vec();
+            assert!(!neighbors.contains(&i));
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            assert_eq!(3, neighbors.len());
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_graph_matches_linear_search() {
+        let mut points: Vec<_> = (0..500)
+            .map(|i| Point2WithId::new(i, f64::from((i * 37) % 83), f64::from((i * 59) % 79)))
+            .collect();
+        points.extend((0..20).map(|i| Point2WithId::new(500 + i, 1., 1.)));
+        let tree = Kdtree::from_vec(points).unwrap();
+        let stored: Vec<_> = tree.iter().collect();
+
+        for &k in &[0, 1, 6, usize::MAX] {
+            let (serial, parallel) = (tree.knn_graph(k), tree.par_knn_graph(k));
+            assert_eq!(serial.offsets, parallel.offsets);
+            assert_eq!(serial.squared_distances, parallel.squared_distances);
+
+            for i in 0..parallel.len() {
+                let mut linear: Vec<f64> = stored
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, q)| squared_euclidean(stored[i].dims(), q.dims()))
+                    .collect();
+                linear.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let row = parallel.squared_distances_of(i);
+                assert_eq!(&linear[..row.len()], row);
+
+                let mut neighbors = parallel.neighbors_of(i).to_vec();
+                for (&j, &d) in neighbors.iter().zip(row) {
+                    assert!(j != i);
+                    assert_eq!(d, squared_euclidean(stored[i].dims(), stored[j].dims()));
+                }
+                neighbors.sort_unstable();
+                neighbors.dedup();
+                assert_eq!(row.len(), neighbors.len());
+            }
+        }
+    }
+}
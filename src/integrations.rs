@@ -0,0 +1,261 @@
+//! `KdtreePointTrait` implementations for the vector types of popular math crates.
+//! Each integration is behind a cargo feature of the same name: `glam`, `nalgebra` and `cgmath`.
+//!
+//! `f64` vectors and points are stored directly, so queries return the native type.
+//! `f32` ones are wrapped in a `WidenedPoint` which keeps the original value next to its
+//! coordinates converted to `f64`, use `into_native` to get it back from a query result.
+
+use kdtree::KdtreePointTrait;
+
+/// `f32` vector together with its coordinates widened to `f64`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WidenedPoint<V, const N: usize> {
+    dims: [f64; N],
+    native: V,
+}
+
+impl<V, const N: usize> WidenedPoint<V, N> {
+    fn from_f32(native: V, coords: &[f32; N]) -> WidenedPoint<V, N> {
+        let mut dims = [0.; N];
+        for (dim, coord) in dims.iter_mut().zip(coords.iter()) {
+            *dim = f64::from(*coord);
+        }
+
+        WidenedPoint { dims, native }
+    }
+
+    pub fn native(&self) -> &V {
+        &self.native
+    }
+
+    pub fn into_native(self) -> V {
+        self.native
+    }
+}
+
+impl<V: Clone + PartialEq, const N: usize> KdtreePointTrait for WidenedPoint<V, N> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+#[cfg(any(feature = "glam", feature = "cgmath"))]
+macro_rules! impl_for_fixed_array_types {
+    ($($native:ty => $n:expr),*) => {
+        $(
+            impl KdtreePointTrait for $native {
+                #[inline]
+                fn dims(&self) -> &[f64] {
+                    AsRef::<[f64; $n]>::as_ref(self)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(any(feature = "glam", feature = "cgmath"))]
+macro_rules! impl_widening_from {
+    ($($native:ty => $n:expr),*) => {
+        $(
+            impl From<$native> for WidenedPoint<$native, $n> {
+                fn from(native: $native) -> Self {
+                    let coords = *AsRef::<[f32; $n]>::as_ref(&native);
+                    WidenedPoint::from_f32(native, &coords)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use super::*;
+    use glam::{DVec2, DVec3, DVec4, Vec2, Vec3, Vec4};
+
+    impl_for_fixed_array_types!(DVec2 => 2, DVec3 => 3, DVec4 => 4);
+    impl_widening_from!(Vec2 => 2, Vec3 => 3, Vec4 => 4);
+}
+
+#[cfg(feature = "cgmath")]
+mod cgmath_impls {
+    use super::*;
+    use cgmath::{Point1, Point2, Point3, Vector1, Vector2, Vector3, Vector4};
+
+    impl_for_fixed_array_types!(
+        Point1<f64> => 1, Point2<f64> => 2, Point3<f64> => 3,
+        Vector1<f64> => 1, Vector2<f64> => 2, Vector3<f64> => 3, Vector4<f64> => 4
+    );
+    impl_widening_from!(
+        Point1<f32> => 1, Point2<f32> => 2, Point3<f32> => 3,
+        Vector1<f32> => 1, Vector2<f32> => 2, Vector3<f32> => 3, Vector4<f32> => 4
+    );
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use super::*;
+    use nalgebra::{Point, SVector};
+
+    impl<const D: usize> KdtreePointTrait for Point<f64, D> {
+        #[inline]
+        fn dims(&self) -> &[f64] {
+            self.coords.as_slice()
+        }
+    }
+
+    impl<const D: usize> KdtreePointTrait for SVector<f64, D> {
+        #[inline]
+        fn dims(&self) -> &[f64] {
+            self.as_slice()
+        }
+    }
+
+    impl<const D: usize> From<Point<f32, D>> for WidenedPoint<Point<f32, D>, D> {
+        fn from(native: Point<f32, D>) -> Self {
+            let coords: [f32; D] = native.coords.into();
+            WidenedPoint::from_f32(native, &coords)
+        }
+    }
+
+    impl<const D: usize> From<SVector<f32, D>> for WidenedPoint<SVector<f32, D>, D> {
+        fn from(native: SVector<f32, D>) -> Self {
+            let coords: [f32; D] = native.into();
+            WidenedPoint::from_f32(native, &coords)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kdtree;
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn glam_vectors() {
+        use glam::{DVec3, Vec2};
+
+        let mut points = vec![DVec3::ZERO, DVec3::new(1., 2., 3.)];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found: DVec3 = tree.nearest_search(&DVec3::new(1., 1., 3.));
+        assert_eq!(DVec3::new(1., 2., 3.), found);
+
+        let mut points: Vec<WidenedPoint<Vec2, 2>> =
+            vec![Vec2::ZERO.into(), Vec2::new(0.5, 4.).into()];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found: Vec2 = tree.nearest_search(&Vec2::new(0., 3.).into()).into_native();
+        assert_eq!(Vec2::new(0.5, 4.), found);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn glam_4d_vectors() {
+        use glam::{DVec4, Vec4};
+
+        let mut points = vec![
+            DVec4::ZERO,
+            DVec4::new(1., 2., 3., 4.),
+            DVec4::new(1., 2., 3., -4.),
+        ];
+        let mut tree = Kdtree::new(&mut points).unwrap();
+        tree.insert_node(DVec4::new(0., 0., 0., 1.));
+        let found: DVec4 = tree.nearest_search(&DVec4::new(1., 2., 3., 3.));
+        assert_eq!(DVec4::new(1., 2., 3., 4.), found);
+        assert_eq!(
+            DVec4::new(0., 0., 0., 1.),
+            tree.nearest_search(&DVec4::new(0., 0., 0., 0.9))
+        );
+        assert_eq!(
+            2,
+            tree.within(&DVec4::ZERO, 1., &crate::distance::squared_euclidean)
+                .len()
+        );
+
+        let mut points: Vec<WidenedPoint<Vec4, 4>> =
+            vec![Vec4::ZERO.into(), Vec4::new(0.5, 4., 1., -2.).into()];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found: Vec4 = tree
+            .nearest_search(&Vec4::new(0., 3., 1., -2.).into())
+            .into_native();
+        assert_eq!(Vec4::new(0.5, 4., 1., -2.), found);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_points_and_vectors() {
+        use nalgebra::{Point3, Vector2};
+
+        let mut points = vec![Point3::new(0., 0., 0.), Point3::new(1., 2., 3.)];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found: Point3<f64> = tree.nearest_search(&Point3::new(1., 1., 3.));
+        assert_eq!(Point3::new(1., 2., 3.), found);
+
+        let mut points: Vec<WidenedPoint<Vector2<f32>, 2>> =
+            vec![Vector2::new(0., 0.).into(), Vector2::new(0.5, 4.).into()];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found = tree.nearest_search(&Vector2::new(0., 1.).into()).into_native();
+        assert_eq!(Vector2::new(0., 0.), found);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_4d_points_and_vectors() {
+        use nalgebra::{Point4, Vector4};
+
+        let mut points = vec![Point4::new(0., 0., 0., 0.), Point4::new(1., 2., 3., 4.)];
+        let mut tree = Kdtree::new(&mut points).unwrap();
+        tree.insert_node(Point4::new(1., 2., 3., -4.));
+        let found: Point4<f64> = tree.nearest_search(&Point4::new(1., 2., 3., -3.));
+        assert_eq!(Point4::new(1., 2., 3., -4.), found);
+
+        let mut points: Vec<WidenedPoint<Vector4<f32>, 4>> = vec![
+            Vector4::new(0., 0., 0., 0.).into(),
+            Vector4::new(0.5, 4., 1., -2.).into(),
+        ];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found = tree
+            .nearest_search(&Vector4::new(0., 3., 1., -2.).into())
+            .into_native();
+        assert_eq!(Vector4::new(0.5, 4., 1., -2.), found);
+    }
+
+    #[cfg(feature = "cgmath")]
+    #[test]
+    fn cgmath_points_and_vectors() {
+        use cgmath::{Point2, Vector3};
+
+        let mut points = vec![Point2::new(0., 0.), Point2::new(1., 2.)];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found: Point2<f64> = tree.nearest_search(&Point2::new(1., 1.5));
+        assert_eq!(Point2::new(1., 2.), found);
+
+        let mut points: Vec<WidenedPoint<Vector3<f32>, 3>> =
+            vec![Vector3::new(0., 0., 0.).into(), Vector3::new(0.5, 4., 1.).into()];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found = tree.nearest_search(&Vector3::new(0., 3., 1.).into()).into_native();
+        assert_eq!(Vector3::new(0.5, 4., 1.), found);
+    }
+
+    #[cfg(feature = "cgmath")]
+    #[test]
+    fn cgmath_4d_vectors() {
+        use cgmath::Vector4;
+
+        let mut points = vec![Vector4::new(0., 0., 0., 0.), Vector4::new(1., 2., 3., 4.)];
+        let mut tree = Kdtree::new(&mut points).unwrap();
+        tree.insert_node(Vector4::new(1., 2., 3., -4.));
+        let found: Vector4<f64> = tree.nearest_search(&Vector4::new(1., 2., 3., -3.));
+        assert_eq!(Vector4::new(1., 2., 3., -4.), found);
+
+        let mut points: Vec<WidenedPoint<Vector4<f32>, 4>> = vec![
+            Vector4::new(0., 0., 0., 0.).into(),
+            Vector4::new(0.5, 4., 1., -2.).into(),
+        ];
+        let tree = Kdtree::new(&mut points).unwrap();
+        let found = tree
+            .nearest_search(&Vector4::new(0., 3., 1., -2.).into())
+            .into_native();
+        assert_eq!(Vector4::new(0.5, 4., 1., -2.), found);
+    }
+}
@@ -0,0 +1,245 @@
+//! Iterators over the points stored in a tree and over the regions its nodes own.
+
+use std::iter::{FromIterator, FusedIterator};
+use std::slice;
+use std::vec;
+
+use bounds::Bounds;
+use kdtree::{Kdtree, KdtreeNode, KdtreePointTrait};
+
+/// Borrowing iterator over the points of a tree, in storage order. Returned by `Kdtree::iter`.
+pub struct Iter<'a, KdtreePoint: 'a> {
+    nodes: slice::Iter<'a, KdtreeNode<KdtreePoint>>,
+}
+
+impl<'a, KdtreePoint> Iterator for Iter<'a, KdtreePoint> {
+    type Item = &'a KdtreePoint;
+
+    fn next(&mut self) -> Option<&'a KdtreePoint> {
+        self.nodes.next().map(|node| &node.point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+}
+
+impl<'a, KdtreePoint> ExactSizeIterator for Iter<'a, KdtreePoint> {}
+impl<'a, KdtreePoint> FusedIterator for Iter<'a, KdtreePoint> {}
+
+/// Owning iterator over the points of a tree, in storage order.
+pub struct IntoIter<KdtreePoint> {
+    nodes: vec::IntoIter<KdtreeNode<KdtreePoint>>,
+}
+
+impl<KdtreePoint> Iterator for IntoIter<KdtreePoint> {
+    type Item = KdtreePoint;
+
+    fn next(&mut self) -> Option<KdtreePoint> {
+        self.nodes.next().map(|node| node.point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+}
+
+impl<KdtreePoint> ExactSizeIterator for IntoIter<KdtreePoint> {}
+impl<KdtreePoint> FusedIterator for IntoIter<KdtreePoint> {}
+
+/// A stored point together with the axis aligned region its node owns.
+///
+/// The root owns the bounding box of all stored points, every child owns the part of its
+/// parent's region on its side of the parent's split. `min` and `max` hold one entry per dimension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell<'a, KdtreePoint: 'a> {
+    pub point: &'a KdtreePoint,
+    pub min: Vec<f64>,
+    pub max: Vec<f64>,
+    /// Dimension the node splits its region on.
+    pub dimension: usize,
+    pub split_on: f64,
+}
+
+/// Depth first iterator over the cells of a tree, returned by `Kdtree::iter_cells`.
+pub struct Cells<'a, KdtreePoint: 'a> {
+    tree: &'a Kdtree<KdtreePoint>,
+    to_visit: Vec<(usize, Bounds)>,
+}
+
+impl<'a, KdtreePoint: KdtreePointTrait> Iterator for Cells<'a, KdtreePoint> {
+    type Item = Cell<'a, KdtreePoint>;
+
+    fn next(&mut self) -> Option<Cell<'a, KdtreePoint>> {
+        let (index, bounds) = self.to_visit.pop()?;
+        let node = &self.tree.nodes[index];
+
+        if let Some(right) = node.right_node {
+            let right_bounds = bounds.clone_moving_min(node.split_on, node.dimension);
+            self.to_visit.push((right, right_bounds));
+        }
+        if let Some(left) = node.left_node {
+            let left_bounds = bounds.clone_moving_max(node.split_on, node.dimension);
+            self.to_visit.push((left, left_bounds));
+        }
+
+        let dimensions = node.point.dims().len();
+        Some(Cell {
+            point: &node.point,
+            min: bounds.bounds[..dimensions].iter().map(|b| b.0).collect(),
+            max: bounds.bounds[..dimensions].iter().map(|b| b.1).collect(),
+            dimension: node.dimension,
+            split_on: node.split_on,
+        })
+    }
+}
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Number of points stored in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// A tree always holds at least one point, this is here for completeness.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterates over all stored points without allocating, in storage order.
+    pub fn iter(&self) -> Iter<'_, KdtreePoint> {
+        Iter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over every node's point together with the region it owns, parents before children.
+    pub fn iter_cells(&self) -> Cells<'_, KdtreePoint> {
+        let bounds = Bounds::new_from_dims(self.nodes.iter().map(|node| node.point.dims()));
+
+        Cells {
+            tree: self,
+            to_visit: vec![(0, bounds)],
+        }
+    }
+}
+
+impl<'a, KdtreePoint: KdtreePointTrait> IntoIterator for &'a Kdtree<KdtreePoint> {
+    type Item = &'a KdtreePoint;
+    type IntoIter = Iter<'a, KdtreePoint>;
+
+    fn into_iter(self) -> Iter<'a, KdtreePoint> {
+        self.iter()
+    }
+}
+
+impl<KdtreePoint: KdtreePointTrait> IntoIterator for Kdtree<KdtreePoint> {
+    type Item = KdtreePoint;
+    type IntoIter = IntoIter<KdtreePoint>;
+
+    fn into_iter(self) -> IntoIter<KdtreePoint> {
+        IntoIter {
+            nodes: self.nodes.into_iter(),
+        }
+    }
+}
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Builds a tree with the default settings from the points of `iter`, `None` if there are none.
+    /// The non panicking version of `collect`, for iterators that may be empty.
+    pub fn try_from_iter<I>(iter: I) -> Option<Kdtree<KdtreePoint>>
+    where
+        I: IntoIterator<Item = KdtreePoint>,
+    {
+        Kdtree::from_vec(iter.into_iter().collect())
+    }
+}
+
+/// Builds a tree with the default settings, like `Kdtree::from_vec`.
+///
+/// # Panics
+///
+/// If the iterator yields no points, use `Kdtree::try_from_iter` to handle that case.
+impl<KdtreePoint: KdtreePointTrait> FromIterator<KdtreePoint> for Kdtree<KdtreePoint> {
+    fn from_iter<I: IntoIterator<Item = KdtreePoint>>(iter: I) -> Kdtree<KdtreePoint> {
+        Kdtree::try_from_iter(iter).expect(
+            "cannot collect a tree from no points, use Kdtree::try_from_iter for iterators that may be empty",
+        )
+    }
+}
+
+/// Adds the points with `insert_nodes_and_rebuild`, so the tree ends up balanced.
+impl<KdtreePoint: KdtreePointTrait> Extend<KdtreePoint> for Kdtree<KdtreePoint> {
+    fn extend<I: IntoIterator<Item = KdtreePoint>>(&mut self, iter: I) {
+        let mut points: Vec<KdtreePoint> = iter.into_iter().collect();
+        if !points.is_empty() {
+            self.insert_nodes_and_rebuild(&mut points);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::Point2WithId;
+
+    fn points() -> Vec<Point2WithId> {
+        (0..20)
+            .map(|i| Point2WithId::new(i, f64::from(i % 4), f64::from(i / 4)))
+            .collect()
+    }
+
+    #[test]
+    fn iterates_over_every_stored_point() {
+        let mut tree = Kdtree::new(&mut points()).unwrap();
+        tree.insert_node(Point2WithId::new(20, 10., 10.));
+        tree.insert_node(Point2WithId::new(20, 10., 10.));
+
+        assert_eq!(21, tree.len());
+        assert_eq!(21, tree.iter().len());
+
+        let mut ids: Vec<i32> = (&tree).into_iter().map(|p| p.id).collect();
+        ids.sort_unstable();
+        assert_eq!((0..21).collect::<Vec<_>>(), ids);
+
+        let mut owned: Vec<i32> = tree.into_iter().map(|p| p.id).collect();
+        owned.sort_unstable();
+        assert_eq!((0..21).collect::<Vec<_>>(), owned);
+    }
+
+    #[test]
+    fn collects_into_and_extends_a_tree() {
+        let mut tree: Kdtree<Point2WithId> = points().into_iter().take(10).collect();
+        assert_eq!(10, tree.len());
+
+        tree.extend(points().into_iter().skip(10));
+        assert_eq!(20, tree.len());
+        assert_eq!(0, tree.stats().inserts_since_last_rebuild);
+        assert!(tree.validate().is_ok());
+
+        tree.extend(vec![]);
+        assert_eq!(20, tree.len());
+
+        let filtered = points().into_iter().filter(|p| p.id > 5);
+        assert_eq!(14, Kdtree::try_from_iter(filtered).unwrap().len());
+        assert!(Kdtree::try_from_iter(points().into_iter().filter(|p| p.id > 50)).is_none());
+    }
+
+    #[test]
+    fn cells_contain_their_points() {
+        let mut tree = Kdtree::new(&mut points()).unwrap();
+        tree.insert_node(Point2WithId::new(20, 1.5, 2.5));
+
+        let cells: Vec<_> = tree.iter_cells().collect();
+        assert_eq!(tree.len(), cells.len());
+        assert_eq!(vec![0., 0.], cells[0].min);
+        assert_eq!(vec![3., 4.], cells[0].max);
+
+        for cell in &cells {
+            let inside = tree.within_box(&cell.min, &cell.max);
+            assert!(inside.contains(&cell.point));
+            for d in 0..2 {
+                assert!(cell.min[d] <= cell.point.dims()[d] && cell.point.dims()[d] <= cell.max[d]);
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! All pairs of points within a radius, between two trees or within one.
+//!
+//! Both trees are walked together. Every node gets the bounding box of its whole subtree, and a
+//! pair of subtrees is skipped as soon as the gap between their boxes is larger than the radius,
+//! so far apart regions are never compared point by point.
+
+use distance::squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+// Bounding box of every node's subtree, indexed by node.
+pub(crate) struct SubtreeBoxes {
+    dimensions: usize,
+    min: Vec<f64>,
+    max: Vec<f64>,
+}
+
+impl SubtreeBoxes {
+    pub(crate) fn new<P: KdtreePointTrait>(tree: &Kdtree<P>) -> SubtreeBoxes {
+        let dimensions = tree.nodes[0].point.dims().len();
+        let mut boxes = SubtreeBoxes {
+            dimensions,
+            min: vec![f64::INFINITY; tree.nodes.len() * dimensions],
+            max: vec![f64::NEG_INFINITY; tree.nodes.len() * dimensions],
+        };
+        boxes.fill(tree, 0);
+
+        boxes
+    }
+
+    fn fill<P: KdtreePointTrait>(&mut self, tree: &Kdtree<P>, index: usize) {
+        let node = &tree.nodes[index];
+        let d = self.dimensions;
+        for (i, value) in node.point.dims().iter().enumerate() {
+            self.min[index * d + i] = *value;
+            self.max[index * d + i] = *value;
+        }
+
+        for child in node.left_node.iter().chain(node.right_node.iter()) {
+            self.fill(tree, *child);
+            for i in 0..d {
+                self.min[index * d + i] = self.min[index * d + i].min(self.min[child * d + i]);
+                self.max[index * d + i] = self.max[index * d + i].max(self.max[child * d + i]);
+            }
+        }
+    }
+
+    pub(crate) fn bounds(&self, index: usize) -> (&[f64], &[f64]) {
+        let range = index * self.dimensions..(index + 1) * self.dimensions;
+        (&self.min[range.clone()], &self.max[range])
+    }
+
+    pub(crate) fn squared_distance_to_point(&self, index: usize, point: &[f64]) -> f64 {
+        let (min, max) = self.bounds(index);
+        point
+            .iter()
+            .zip(min.iter().zip(max))
+            .map(|(x, (lo, hi))| {
+                let gap = (lo - x).max(x - hi).max(0.);
+                gap * gap
+            })
+            .sum()
+    }
+
+    pub(crate) fn squared_distance_to(
+        &self,
+        index: usize,
+        other: &SubtreeBoxes,
+        other_index: usize,
+    ) -> f64 {
+        let (min, max) = self.bounds(index);
+        let (other_min, other_max) = other.bounds(other_index);
+        (0..min.len())
+            .map(|i| {
+                let gap = (other_min[i] - max[i]).max(min[i] - other_max[i]).max(0.);
+                gap * gap
+            })
+            .sum()
+    }
+}
+
+pub(crate) fn children<P>(tree: &Kdtree<P>, index: usize) -> impl Iterator<Item = usize> {
+    let node = &tree.nodes[index];
+    node.left_node.into_iter().chain(node.right_node)
+}
+
+struct Join<'a, P: 'a, Q: 'a> {
+    a: &'a Kdtree<P>,
+    b: &'a Kdtree<Q>,
+    a_boxes: &'a SubtreeBoxes,
+    b_boxes: &'a SubtreeBoxes,
+    squared_radius: f64,
+    pairs: Vec<(usize, usize)>,
+}
+
+impl<'a, P: KdtreePointTrait, Q: KdtreePointTrait> Join<'a, P, Q> {
+    // Pairs between the subtrees at `a` and at `b`.
+    fn subtrees(&mut self, a: usize, b: usize) {
+        if self.a_boxes.squared_distance_to(a, self.b_boxes, b) > self.squared_radius {
+            return;
+        }
+
+        self.point_pair(a, b);
+        for child in children(self.b, b) {
+            self.a_point_with_subtree(a, child);
+        }
+        for child in children(self.a, a) {
+            self.b_point_with_subtree(b, child);
+        }
+        for a_child in children(self.a, a) {
+            for b_child in children(self.b, b) {
+                self.subtrees(a_child, b_child);
+            }
+        }
+    }
+
+    // Unordered pairs within the subtree at `a`, only used when both trees are the same.
+    fn self_subtree(&mut self, a: usize) {
+        for child in children(self.a, a) {
+            self.a_point_with_subtree(a, child);
+            self.self_subtree(child);
+        }
+
+        let node = &self.a.nodes[a];
+        if let (Some(left), Some(right)) = (node.left_node, node.right_node) {
+            self.subtrees(left, right);
+        }
+    }
+
+    fn a_point_with_subtree(&mut self, a: usize, b: usize) {
+        let point = self.a.nodes[a].point.dims();
+        if self.b_boxes.squared_distance_to_point(b, point) > self.squared_radius {
+            return;
+        }
+
+        self.point_pair(a, b);
+        for child in children(self.b, b) {
+            self.a_point_with_subtree(a, child);
+        }
+    }
+
+    fn b_point_with_subtree(&mut self, b: usize, a: usize) {
+        let point = self.b.nodes[b].point.dims();
+        if self.a_boxes.squared_distance_to_point(a, point) > self.squared_radius {
+            return;
+        }
+
+        self.point_pair(a, b);
+        for child in children(self.a, a) {
+            self.b_point_with_subtree(b, child);
+        }
+    }
+
+    fn point_pair(&mut self, a: usize, b: usize) {
+        let distance =
+            squared_euclidean(self.a.nodes[a].point.dims(), self.b.nodes[b].point.dims());
+        if distance <= self.squared_radius {
+            self.pairs.push((a, b));
+        }
+    }
+}
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Every pair `(a, b)` with `a` from this tree and `b` from `other`
+    /// whose euclidean distance is at most `radius`, in no particular order.
+    /// None for a negative or NaN radius, fails when the trees have different dimension counts.
+    pub fn pairs_within<'a, Other: KdtreePointTrait>(
+        &'a self,
+        other: &'a Kdtree<Other>,
+        radius: f64,
+    ) -> Result<Vec<(&'a KdtreePoint, &'a Other)>, KdtreeError> {
+        let dimensions = self.nodes[0].point.dims().len();
+        let other_dimensions = other.nodes[0].point.dims().len();
+        if dimensions != other_dimensions {
+            return Err(KdtreeError::DimensionMismatch {
+                expected: dimensions,
+                actual: other_dimensions,
+            });
+        }
+        // a negative radius would square to a positive one.
+        if radius.is_nan() || radius < 0. {
+            return Ok(vec![]);
+        }
+
+        let (a_boxes, b_boxes) = (SubtreeBoxes::new(self), SubtreeBoxes::new(other));
+        let mut join = Join {
+            a: self,
+            b: other,
+            a_boxes: &a_boxes,
+            b_boxes: &b_boxes,
+            squared_radius: radius * radius,
+            pairs: vec![],
+        };
+        join.subtrees(0, 0);
+
+        Ok(join
+            .pairs
+            .into_iter()
+            .map(|(a, b)| (&self.nodes[a].point, &other.nodes[b].point))
+            .collect())
+    }
+
+    /// Every unordered pair of distinct stored points whose euclidean distance is at most `radius`,
+    /// each reported once, in no particular order. None for a negative or NaN radius.
+    pub fn self_pairs_within(&self, radius: f64) -> Vec<(&KdtreePoint, &KdtreePoint)> {
+        if radius.is_nan() || radius < 0. {
+            return vec![];
+        }
+
+        let boxes = SubtreeBoxes::new(self);
+        let mut join = Join {
+            a: self,
+            b: self,
+            a_boxes: &boxes,
+            b_boxes: &boxes,
+            squared_radius: radius * radius,
+            pairs: vec![],
+        };
+        join.self_subtree(0);
+
+        join.pairs
+            .into_iter()
+            .map(|(a, b)| (&self.nodes[a].point, &self.nodes[b].point))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::squared_euclidean;
+    use error::KdtreeError;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::{Point2WithId, Point3WithId};
+
+    fn scatter(count: i32, seed: i32) -> Vec<Point3WithId> {
+        (0..count)
+            .map(|i| {
+                let x = f64::from((i * 37 + seed) % 101) / 10.;
+                let y = f64::from((i * 53 + seed * 7) % 89) / 10.;
+                let z = f64::from((i * 71 + seed * 3) % 23) / 10.;
+                Point3WithId::new(i, x, y, z)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pairs_match_linear_search() {
+        let (a, b) = (scatter(150, 1), scatter(120, 5));
+        let (tree_a, tree_b) = (
+            Kdtree::from_vec(a.clone()).unwrap(),
+            Kdtree::from_vec(b.clone()).unwrap(),
+        );
+
+        let mut found: Vec<(i32, i32)> = tree_a
+            .pairs_within(&tree_b, 0.8)
+            .unwrap()
+            .iter()
+            .map(|(p, q)| (p.id, q.id))
+            .collect();
+        found.sort_unstable();
+
+        let mut expected = vec![];
+        for p in &a {
+            for q in &b {
+                if squared_euclidean(p.dims(), q.dims()) <= 0.8 * 0.8 {
+                    expected.push((p.id, q.id));
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn self_join_reports_each_pair_once() {
+        let mut points = scatter(200, 3);
+        points.push(Point3WithId::new(200, 0., 0., 0.));
+        points.push(Point3WithId::new(201, 0., 0., 0.));
+        let tree = Kdtree::from_vec(points.clone()).unwrap();
+
+        let mut found: Vec<(i32, i32)> = tree
+            .self_pairs_within(0.7)
+            .iter()
+            .map(|(p, q)| (p.id.min(q.id), p.id.max(q.id)))
+            .collect();
+        found.sort_unstable();
+
+        let mut expected = vec![];
+        for (i, p) in points.iter().enumerate() {
+            for q in &points[i + 1..] {
+                if squared_euclidean(p.dims(), q.dims()) <= 0.7 * 0.7 {
+                    expected.push((p.id.min(q.id), p.id.max(q.id)));
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert!(expected.contains(&(200, 201)));
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn trees_of_different_point_types() {
+        let tree_a = Kdtree::from_vec(vec![Point2WithId::new(0, 0., 0.)]).unwrap();
+        let tree_b = Kdtree::from_vec(vec![[0.5, 0.], [2., 0.]]).unwrap();
+
+        let pairs = tree_a.pairs_within(&tree_b, 1.).unwrap();
+        assert_eq!(1, pairs.len());
+        assert_eq!([0.5, 0.], *pairs[0].1);
+    }
+
+    #[test]
+    fn negative_or_nan_radius_finds_no_pairs() {
+        let tree_a = Kdtree::from_vec(vec![[0., 0.], [0.5, 0.]]).unwrap();
+        let tree_b = Kdtree::from_vec(vec![[0.5, 0.]]).unwrap();
+
+        for &radius in &[-1., f64::NAN] {
+            assert!(tree_a.pairs_within(&tree_b, radius).unwrap().is_empty());
+            assert!(tree_a.self_pairs_within(radius).is_empty());
+        }
+    }
+
+    #[test]
+    fn trees_of_different_dimensions() {
+        let tree_a = Kdtree::from_vec(vec![[0., 0., 0.]]).unwrap();
+        let tree_b = Kdtree::from_vec(vec![[0., 0.]]).unwrap();
+
+        match tree_a.pairs_within(&tree_b, 1.) {
+            Err(KdtreeError::DimensionMismatch { expected, actual }) => {
+                assert_eq!((3, 2), (expected, actual))
+            }
+            _ => panic!("joined trees of different dimensions"),
+        }
+    }
+}
@@ -1,25 +1,106 @@
 use crate::bounds::*;
+use crate::builder::TreeSettings;
 use crate::distance::*;
-use crate::partition;
+use crate::stats::{QueryCounter, QueryStats};
 
 use std::cmp;
 use std::collections::BTreeSet;
 
-pub trait KdtreePointTrait: Copy + PartialEq {
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+pub trait KdtreePointTrait: Clone + PartialEq {
     fn dims(&self) -> &[f64];
 }
 
+/// With the `serde` feature a built tree can be serialized and later deserialized
+/// ready for querying, without calling `rebuild_tree`. Deserializing runs `validate`
+/// and fails on a tree that does not pass it.
+///
+/// The tree is `Send` and `Sync` whenever its points are. Queries only take `&self`, so one tree
+/// can be shared between threads through an `Arc` and queried concurrently. Inserting needs `&mut self`
+/// and may rebuild the whole tree, use `ConcurrentKdtree` to keep answering queries while that happens.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Kdtree<KdtreePoint> {
-    nodes: Vec<KdtreeNode<KdtreePoint>>,
+    pub(crate) nodes: Vec<KdtreeNode<KdtreePoint>>,
 
     node_adding_dimension: usize,
-    node_depth_during_last_rebuild: usize,
+    pub(crate) node_depth_during_last_rebuild: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     current_node_depth: usize,
+    pub(crate) inserts_since_last_rebuild: usize,
+    settings: TreeSettings,
+    // smallest box holding every stored point, one `(min, max)` pair per dimension.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) bounding_box: Vec<(f64, f64)>,
+}
+
+// Fields of a serialized tree, checked with `validate` before they become a `Kdtree`.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedKdtree<KdtreePoint> {
+    nodes: Vec<KdtreeNode<KdtreePoint>>,
+    node_adding_dimension: usize,
+    node_depth_during_last_rebuild: usize,
+    #[serde(default)]
+    inserts_since_last_rebuild: usize,
+    #[serde(default)]
+    settings: TreeSettings,
+}
+
+/// Fails with the `validate` error when the nodes do not form a valid tree, so a corrupted
+/// or hand edited input cannot make later queries panic.
+#[cfg(feature = "serde")]
+impl<'de, KdtreePoint> Deserialize<'de> for Kdtree<KdtreePoint>
+where
+    KdtreePoint: KdtreePointTrait + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let unchecked = UncheckedKdtree::<KdtreePoint>::deserialize(deserializer)?;
+        let mut tree = Kdtree {
+            nodes: unchecked.nodes,
+            node_adding_dimension: unchecked.node_adding_dimension,
+            node_depth_during_last_rebuild: unchecked.node_depth_during_last_rebuild,
+            current_node_depth: 0,
+            inserts_since_last_rebuild: unchecked.inserts_since_last_rebuild,
+            settings: unchecked.settings,
+            bounding_box: vec![],
+        };
+
+        tree.validate().map_err(de::Error::custom)?;
+        tree.settings.check().map_err(de::Error::custom)?;
+        let dimensions = tree.nodes[0].point.dims().len();
+        if tree.node_adding_dimension >= dimensions {
+            return Err(de::Error::custom(format!(
+                "next insert splits on dimension {} of a {} dimensional tree",
+                tree.node_adding_dimension, dimensions
+            )));
+        }
+
+        tree.bounding_box =
+            Bounds::new_from_dims(tree.nodes.iter().map(|node| node.point.dims())).bounds;
+
+        Ok(tree)
+    }
 }
 
 impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
-    pub fn new(mut points: &mut [KdtreePoint]) -> Option<Kdtree<KdtreePoint>> {
-        if points.len() == 0 {
+    /// Builds a tree with the default settings, see `KdtreeBuilder` for the others.
+    pub fn new(points: &mut [KdtreePoint]) -> Option<Kdtree<KdtreePoint>> {
+        Kdtree::new_with_settings(points, TreeSettings::default())
+    }
+
+    /// Builds a tree with the default settings from owned points, leaving the caller's data untouched.
+    pub fn from_vec(mut points: Vec<KdtreePoint>) -> Option<Kdtree<KdtreePoint>> {
+        Kdtree::new(&mut points)
+    }
+
+    pub(crate) fn new_with_settings(
+        points: &mut [KdtreePoint],
+        settings: TreeSettings,
+    ) -> Option<Kdtree<KdtreePoint>> {
+        if points.is_empty() {
             return None;
         }
 
@@ -28,21 +109,35 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
             node_adding_dimension: 0,
             node_depth_during_last_rebuild: 0,
             current_node_depth: 0,
+            inserts_since_last_rebuild: 0,
+            settings,
+            bounding_box: vec![],
         };
 
-        tree.rebuild_tree(&mut points);
+        tree.rebuild_tree(points);
 
         Some(tree)
     }
 
+    /// Settings the tree was built with, also used when it rebuilds itself.
+    pub fn settings(&self) -> &TreeSettings {
+        &self.settings
+    }
+
     pub fn rebuild_tree(&mut self, points: &mut [KdtreePoint]) {
         self.nodes.clear();
 
         self.node_depth_during_last_rebuild = 0;
         self.current_node_depth = 0;
+        self.inserts_since_last_rebuild = 0;
+
+        let mut rect = Bounds::new_from_points(points);
+        self.bounding_box = rect.bounds.clone();
+        self.build_tree(points, &mut rect, 1);
 
-        let rect = Bounds::new_from_points(points);
-        self.build_tree(points, &rect, 1);
+        if self.settings.optimize_layout {
+            self.optimize_layout();
+        }
     }
 
     /// Can be used if you are sure that the tree is degenerated or if you will never again insert the nodes into the tree.
@@ -54,11 +149,56 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
     }
 
     pub fn nearest_search(&self, node: &KdtreePoint) -> KdtreePoint {
+        let nearest_neighbor = self.nearest_search_counted(node, &mut ());
+
+        self.nodes[nearest_neighbor].point.clone()
+    }
+
+    /// `nearest_search` which also reports how much work the query took.
+    pub fn nearest_search_with_stats(&self, node: &KdtreePoint) -> (KdtreePoint, QueryStats) {
+        let mut stats = QueryStats::default();
+        let nearest_neighbor = self.nearest_search_counted(node, &mut stats);
+
+        (self.nodes[nearest_neighbor].point.clone(), stats)
+    }
+
+    fn nearest_search_counted<S: QueryCounter>(&self, node: &KdtreePoint, stats: &mut S) -> usize {
         let mut nearest_neighbor = 0usize;
-        let mut best_distance = squared_euclidean(node.dims(), &self.nodes[0].point.dims());
-        self.nearest_search_impl(node, 0usize, &mut best_distance, &mut nearest_neighbor);
+        let mut best_distance = squared_euclidean(node.dims(), self.nodes[0].point.dims());
+        stats.distance_evaluated();
+        self.nearest_search_impl(
+            node,
+            0usize,
+            &mut best_distance,
+            &mut nearest_neighbor,
+            stats,
+        );
 
-        self.nodes[nearest_neighbor].point
+        nearest_neighbor
+    }
+
+    /// Returns up to `k` points closest to `node`, nearest first,
+    /// each paired with its squared euclidean distance to `node`.
+    pub fn nearest_k_search(&self, node: &KdtreePoint, k: usize) -> Vec<(&KdtreePoint, f64)> {
+        self.nearest_k_search_by_dims(node.dims(), k)
+    }
+
+    /// Same as `nearest_k_search`, for callers holding bare coordinates rather than a point.
+    pub(crate) fn nearest_k_search_by_dims(
+        &self,
+        dims: &[f64],
+        k: usize,
+    ) -> Vec<(&KdtreePoint, f64)> {
+        let k = k.min(self.nodes.len());
+        let mut nearest = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.nearest_k_search_impl(dims, 0usize, k, &mut nearest);
+        }
+
+        nearest
+            .into_iter()
+            .map(|(index, distance)| (&self.nodes[index].point, distance))
+            .collect()
     }
 
     pub fn within<F>(
@@ -69,10 +209,48 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
     ) -> Vec<&KdtreePoint>
     where
         F: Fn(&[f64], &[f64]) -> f64,
+    {
+        self.within_counted(node, search_radius, distance_function, &mut ())
+            .into_iter()
+            .map(|x| &self.nodes[x].point)
+            .collect()
+    }
+
+    /// `within` which also reports how much work the query took.
+    pub fn within_with_stats<F>(
+        &self,
+        node: &KdtreePoint,
+        search_radius: f64,
+        distance_function: &F,
+    ) -> (Vec<&KdtreePoint>, QueryStats)
+    where
+        F: Fn(&[f64], &[f64]) -> f64,
+    {
+        let mut stats = QueryStats::default();
+        let found = self
+            .within_counted(node, search_radius, distance_function, &mut stats)
+            .into_iter()
+            .map(|x| &self.nodes[x].point)
+            .collect();
+
+        (found, stats)
+    }
+
+    fn within_counted<F, S>(
+        &self,
+        node: &KdtreePoint,
+        search_radius: f64,
+        distance_function: &F,
+        stats: &mut S,
+    ) -> BTreeSet<usize>
+    where
+        F: Fn(&[f64], &[f64]) -> f64,
+        S: QueryCounter,
     {
         let mut nearest = BTreeSet::<usize>::new();
         let mut nearest_neighbor = 0usize;
-        let mut best_distance = distance_function(node.dims(), &self.nodes[0].point.dims());
+        let mut best_distance = distance_function(node.dims(), self.nodes[0].point.dims());
+        stats.distance_evaluated();
         self.within_nearest_search_impl(
             node,
             0usize,
@@ -81,9 +259,19 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
             &mut nearest,
             search_radius,
             &distance_function,
+            stats,
         );
 
-        nearest.into_iter().map(|x| &self.nodes[x].point).collect()
+        nearest
+    }
+
+    /// Returns the points lying inside the axis aligned box spanned by `min` and `max`, boundaries included.
+    pub fn within_box(&self, min: &[f64], max: &[f64]) -> Vec<&KdtreePoint> {
+        debug_assert!(min.len() == max.len());
+        let mut found = vec![];
+        self.within_box_impl(min, max, 0usize, &mut found);
+
+        found.into_iter().map(|x| &self.nodes[x].point).collect()
     }
 
     pub fn has_neighbor_in_range(&self, node: &KdtreePoint, range: f64) -> bool {
@@ -93,13 +281,13 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
     }
 
     pub fn distance_squared_to_nearest(&self, node: &KdtreePoint) -> f64 {
-        squared_euclidean(&self.nearest_search(node).dims(), node.dims())
+        squared_euclidean(self.nearest_search(node).dims(), node.dims())
     }
 
     pub fn insert_nodes_and_rebuild(&mut self, nodes_to_add: &mut [KdtreePoint]) {
         let mut pts: Vec<KdtreePoint> = vec![];
         self.gather_points(0, &mut pts);
-        pts.extend(nodes_to_add.iter());
+        pts.extend(nodes_to_add.iter().cloned());
 
         self.rebuild_tree(&mut pts);
     }
@@ -107,9 +295,16 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
     pub fn insert_node(&mut self, node_to_add: KdtreePoint) {
         let mut current_index = 0;
         let dimension = self.node_adding_dimension;
-        let index_of_new_node =
-            self.add_node(node_to_add, dimension, node_to_add.dims()[dimension]);
+        let index_of_new_node = self.add_node(
+            node_to_add.clone(),
+            dimension,
+            node_to_add.dims()[dimension],
+        );
         self.node_adding_dimension = (dimension + 1) % node_to_add.dims().len();
+        for (bound, value) in self.bounding_box.iter_mut().zip(node_to_add.dims()) {
+            bound.0 = bound.0.min(*value);
+            bound.1 = bound.1.max(*value);
+        }
         let mut should_pop_node = false;
 
         let mut depth = 0;
@@ -144,21 +339,27 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
 
         if should_pop_node {
             self.nodes.pop();
+        } else {
+            self.inserts_since_last_rebuild += 1;
         }
 
-        if self.node_depth_during_last_rebuild as f64 * 4.0 < depth as f64 {
+        if self.node_depth_during_last_rebuild as f64 * self.settings.rebuild_depth_factor
+            < depth as f64
+        {
             self.gather_points_and_rebuild();
         }
     }
 
-    fn nearest_search_impl(
+    fn nearest_search_impl<S: QueryCounter>(
         &self,
         p: &KdtreePoint,
         searched_index: usize,
         best_distance_squared: &mut f64,
         best_leaf_found: &mut usize,
+        stats: &mut S,
     ) {
         let node = &self.nodes[searched_index];
+        stats.node_visited();
 
         let splitting_value = node.split_on;
         let point_splitting_dim_value = p.dims()[node.dimension];
@@ -170,10 +371,17 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
         };
 
         if let Some(closer_node) = closer_node {
-            self.nearest_search_impl(p, closer_node, best_distance_squared, best_leaf_found);
+            self.nearest_search_impl(
+                p,
+                closer_node,
+                best_distance_squared,
+                best_leaf_found,
+                stats,
+            );
         }
 
         let distance = squared_euclidean(p.dims(), node.point.dims());
+        stats.distance_evaluated();
         if distance < *best_distance_squared {
             *best_distance_squared = distance;
             *best_leaf_found = searched_index;
@@ -184,14 +392,66 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
                 squared_euclidean(&[splitting_value], &[point_splitting_dim_value]);
 
             if distance_on_single_dimension <= *best_distance_squared {
-                self.nearest_search_impl(p, farther_node, best_distance_squared, best_leaf_found);
+                self.nearest_search_impl(
+                    p,
+                    farther_node,
+                    best_distance_squared,
+                    best_leaf_found,
+                    stats,
+                );
+            } else {
+                stats.subtree_pruned();
+            }
+        }
+    }
+
+    // `nearest` is kept sorted by distance and never grows beyond `k` entries.
+    pub(crate) fn nearest_k_search_impl(
+        &self,
+        p: &[f64],
+        searched_index: usize,
+        k: usize,
+        nearest: &mut Vec<(usize, f64)>,
+    ) {
+        let node = &self.nodes[searched_index];
+
+        let splitting_value = node.split_on;
+        let point_splitting_dim_value = p[node.dimension];
+
+        let (closer_node, farther_node) = if point_splitting_dim_value <= splitting_value {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.nearest_k_search_impl(p, closer_node, k, nearest);
+        }
+
+        let distance = squared_euclidean(p, node.point.dims());
+        if nearest.len() < k || distance < nearest[nearest.len() - 1].1 {
+            let insert_at = nearest
+                .iter()
+                .position(|&(_, d)| distance < d)
+                .unwrap_or(nearest.len());
+            nearest.insert(insert_at, (searched_index, distance));
+            nearest.truncate(k);
+        }
+
+        if let Some(farther_node) = farther_node {
+            let distance_on_single_dimension =
+                squared_euclidean(&[splitting_value], &[point_splitting_dim_value]);
+
+            if nearest.len() < k || distance_on_single_dimension <= nearest[nearest.len() - 1].1 {
+                self.nearest_k_search_impl(p, farther_node, k, nearest);
             }
         }
     }
 
     // Recursively checks the leaves of the head node until the best node is found
     // Logs the nodes that satisfy the search radius until the best node is found
-    fn within_nearest_search_impl<F>(
+    #[allow(clippy::too_many_arguments)]
+    fn within_nearest_search_impl<F, S>(
         &self,
         p: &KdtreePoint,
         searched_index: usize,
@@ -200,10 +460,13 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
         within_nodes: &mut BTreeSet<usize>,
         search_radius: f64,
         distance_function: &F,
+        stats: &mut S,
     ) where
         F: Fn(&[f64], &[f64]) -> f64,
+        S: QueryCounter,
     {
         let node = &self.nodes[searched_index];
+        stats.node_visited();
 
         let splitting_value = node.split_on;
         let point_splitting_dim_value = p.dims()[node.dimension];
@@ -224,10 +487,12 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
                 within_nodes,
                 search_radius,
                 distance_function,
+                stats,
             );
         }
 
         let distance = distance_function(p.dims(), node.point.dims());
+        stats.distance_evaluated();
         if distance <= search_radius {
             within_nodes.insert(searched_index);
         }
@@ -251,7 +516,38 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
                     within_nodes,
                     search_radius,
                     distance_function,
+                    stats,
                 );
+            } else {
+                stats.subtree_pruned();
+            }
+        }
+    }
+
+    fn within_box_impl(
+        &self,
+        min: &[f64],
+        max: &[f64],
+        searched_index: usize,
+        found: &mut Vec<usize>,
+    ) {
+        let node = &self.nodes[searched_index];
+
+        // points equal to the splitting value may sit on either side of it.
+        if let Some(left_node) = node.left_node {
+            if min[node.dimension] <= node.split_on {
+                self.within_box_impl(min, max, left_node, found);
+            }
+        }
+
+        let dims = node.point.dims();
+        if (0..min.len()).all(|i| min[i] <= dims[i] && dims[i] <= max[i]) {
+            found.push(searched_index);
+        }
+
+        if let Some(right_node) = node.right_node {
+            if max[node.dimension] >= node.split_on {
+                self.within_box_impl(min, max, right_node, found);
             }
         }
     }
@@ -263,42 +559,46 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
         self.nodes.len() - 1
     }
 
-    fn build_tree(&mut self, nodes: &mut [KdtreePoint], bounds: &Bounds, depth: usize) -> usize {
-        let splitting_index = partition::partition_sliding_midpoint(
-            nodes,
-            bounds.get_midvalue_of_widest_dim(),
-            bounds.get_widest_dim(),
-        );
-        let pivot_value = nodes[splitting_index].dims()[bounds.get_widest_dim()];
+    fn build_tree(
+        &mut self,
+        nodes: &mut [KdtreePoint],
+        bounds: &mut Bounds,
+        depth: usize,
+    ) -> usize {
+        let (dimension, splitting_index) = self.settings.split_rule.split(nodes, bounds);
+        let pivot_value = nodes[splitting_index].dims()[dimension];
 
-        let node_id = self.add_node(nodes[splitting_index], bounds.get_widest_dim(), pivot_value);
+        let node_id = self.add_node(nodes[splitting_index].clone(), dimension, pivot_value);
         let nodes_len = nodes.len();
+        // children narrow `bounds` in place, it is restored once both are built.
+        let (min, max) = bounds.bounds[dimension];
 
         if splitting_index > 0 {
-            let left_rect = bounds.clone_moving_max(pivot_value, bounds.get_widest_dim());
-            let left_child_id =
-                self.build_tree(&mut nodes[0..splitting_index], &left_rect, depth + 1);
+            bounds.set(dimension, (min, pivot_value));
+            let left_child_id = self.build_tree(&mut nodes[0..splitting_index], bounds, depth + 1);
             self.nodes[node_id].left_node = Some(left_child_id);
         }
 
         if splitting_index < nodes.len() - 1 {
-            let right_rect = bounds.clone_moving_min(pivot_value, bounds.get_widest_dim());
+            bounds.set(dimension, (pivot_value, max));
 
             let right_child_id = self.build_tree(
                 &mut nodes[splitting_index + 1..nodes_len],
-                &right_rect,
+                bounds,
                 depth + 1,
             );
             self.nodes[node_id].right_node = Some(right_child_id);
         }
 
+        bounds.set(dimension, (min, max));
+
         self.node_depth_during_last_rebuild = cmp::max(self.node_depth_during_last_rebuild, depth);
 
         node_id
     }
 
     fn gather_points(&self, current_index: usize, points: &mut Vec<KdtreePoint>) {
-        points.push(self.nodes[current_index].point);
+        points.push(self.nodes[current_index].point.clone());
         if let Some(left_index) = self.nodes[current_index].left_node {
             self.gather_points(left_index, points);
         }
@@ -309,13 +609,15 @@ impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
     }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KdtreeNode<T> {
-    left_node: Option<usize>,
-    right_node: Option<usize>,
+    pub(crate) left_node: Option<usize>,
+    pub(crate) right_node: Option<usize>,
 
-    point: T,
-    dimension: usize,
-    split_on: f64,
+    pub(crate) point: T,
+    pub(crate) dimension: usize,
+    pub(crate) split_on: f64,
 }
 
 impl<T: KdtreePointTrait> KdtreeNode<T> {
@@ -333,6 +635,8 @@ impl<T: KdtreePointTrait> KdtreeNode<T> {
 
 #[cfg(test)]
 mod tests {
+    use crate::builder::KdtreeBuilder;
+    use crate::split::SplitRule;
     use crate::test_common::{Point2WithId, Point3WithId};
 
     use super::*;
@@ -357,23 +661,23 @@ mod tests {
                 vec.push(p);
             }
 
-            let tree = Kdtree::new(&mut qc_value_vec_to_2d_points_vec(&xs)).unwrap();
-
-            let mut to_iterate : Vec<usize> = vec![];
-            to_iterate.push(0);
+            trees_for_every_rule(&qc_value_vec_to_2d_points_vec(&xs)).iter().all(|tree| {
+                let mut to_iterate : Vec<usize> = vec![];
+                to_iterate.push(0);
 
-            while to_iterate.len() > 0 {
-                let last_index = to_iterate.last().unwrap().clone();
-                let ref x = tree.nodes.get(last_index).unwrap();
-                to_iterate.pop();
-                if x.left_node.is_some() {
-                    to_iterate.push(x.left_node.unwrap());
-                }
-                if x.right_node.is_some() {
-                    to_iterate.push(x.right_node.unwrap());
+                while to_iterate.len() > 0 {
+                    let last_index = to_iterate.last().unwrap().clone();
+                    let ref x = tree.nodes.get(last_index).unwrap();
+                    to_iterate.pop();
+                    if x.left_node.is_some() {
+                        to_iterate.push(x.left_node.unwrap());
+                    }
+                    if x.right_node.is_some() {
+                        to_iterate.push(x.right_node.unwrap());
+                    }
                 }
-            }
-            xs.len() == tree.nodes.len()
+                xs.len() == tree.nodes.len()
+            })
         }
     }
 
@@ -384,14 +688,13 @@ mod tests {
             }
 
             let point_vec = qc_value_vec_to_2d_points_vec(&xs);
-            let tree = Kdtree::new(&mut point_vec.clone()).unwrap();
-
-            for p in &point_vec {
-                let found_nn = tree.nearest_search(p);
+            for tree in trees_for_every_rule(&point_vec) {
+                for p in &point_vec {
+                    let found_nn = tree.nearest_search(p);
 
-                assert_eq!(p.id,found_nn.id);
+                    assert_eq!(p.id,found_nn.id);
+                }
             }
-
             true
         }
     }
@@ -404,18 +707,17 @@ mod tests {
 
             let point_vec = qc_value_vec_to_3d_points_vec(&tree);
             let search_points_vec = qc_value_vec_to_3d_points_vec(&search_points);
-            let tree = Kdtree::new(&mut point_vec.clone()).unwrap();
-
-
-            for storage in vec![&point_vec, &search_points_vec] {
-                let storage: &Vec<Point3WithId> = storage;
-                for p in storage {
-                    let tree_result = tree.nearest_search(p);
-                    let linear_result = linear_nn(&point_vec, p).clone();
-                    assert_eq!(tree_result,linear_result, "testing lookup of {:?} linear_result: {:?} tree_result: {:?}", p, linear_result, tree_result);
+            for tree in trees_for_every_rule(&point_vec) {
+
+                for storage in vec![&point_vec, &search_points_vec] {
+                    let storage: &Vec<Point3WithId> = storage;
+                    for p in storage {
+                        let tree_result = tree.nearest_search(p);
+                        let linear_result = linear_nn(&point_vec, p).clone();
+                        assert_eq!(tree_result,linear_result, "testing lookup of {:?} linear_result: {:?} tree_result: {:?}", p, linear_result, tree_result);
+                    }
                 }
             }
-
             true
         }
     }
@@ -428,25 +730,116 @@ mod tests {
 
             let point_vec = qc_value_vec_to_3d_points_vec(&tree);
             let search_points_vec = qc_value_vec_to_3d_points_vec(&search_points);
-            let tree = Kdtree::new(&mut point_vec.clone()).unwrap();
+            for tree in trees_for_every_rule(&point_vec) {
 
+                for storage in vec![&point_vec, &search_points_vec] {
+                    let storage: &Vec<Point3WithId> = storage;
+                    for p in storage {
+                        let mut tree_result = tree.within(p, dist, &squared_euclidean);
+                        let mut linear_result = linear_within(&point_vec, p, dist, &squared_euclidean).collect::<Vec<_>>();
+                        tree_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                        linear_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
-            for storage in vec![&point_vec, &search_points_vec] {
-                let storage: &Vec<Point3WithId> = storage;
-                for p in storage {
-                    let mut tree_result = tree.within(p, dist, &squared_euclidean);
-                    let mut linear_result = linear_within(&point_vec, p, dist, &squared_euclidean).collect::<Vec<_>>();
-                    tree_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                        assert_eq!(tree_result, linear_result, "testing within of {:?} dist: {} linear_result: {:?} tree_result: {:?} for tree_nodes: {:?}", p, dist, linear_result, tree_result, point_vec);
+                    }
+                }
+            }
+            true
+        }
+    }
+
+    quickcheck! {
+        fn tree_nearest_k_same_results_as_linear_3d(tree : Vec<(f64, f64, f64)>, search_points: Vec<(f64, f64, f64)>, k: usize) -> bool {
+            if tree.is_empty() {
+                return true;
+            }
+            let k = k % (tree.len() + 2);
+
+            let point_vec = qc_value_vec_to_3d_points_vec(&tree);
+            let search_points_vec = qc_value_vec_to_3d_points_vec(&search_points);
+            for tree in trees_for_every_rule(&point_vec) {
+                for p in &search_points_vec {
+                    let tree_result = tree.nearest_k_search(p, k);
+                    let mut linear_result = point_vec.iter().map(|x| squared_euclidean(x.dims(), p.dims())).collect::<Vec<_>>();
                     linear_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    linear_result.truncate(k);
 
-                    assert_eq!(tree_result, linear_result, "testing within of {:?} dist: {} linear_result: {:?} tree_result: {:?} for tree_nodes: {:?}", p, dist, linear_result, tree_result, point_vec);
+                    let tree_distances = tree_result.iter().map(|x| x.1).collect::<Vec<_>>();
+                    assert_eq!(tree_distances, linear_result, "testing nearest {} of {:?}", k, p);
+                    for (found, distance) in tree_result {
+                        assert_eq!(squared_euclidean(found.dims(), p.dims()), distance);
+                    }
                 }
             }
+            true
+        }
+    }
+
+    #[test]
+    fn nearest_k_returns_nearest_first() {
+        let mut vec = vec![
+            Point2WithId::new(0, 0., 0.),
+            Point2WithId::new(1, 3., 0.),
+            Point2WithId::new(2, 1., 0.),
+            Point2WithId::new(3, -2., 0.),
+        ];
+        let tree = Kdtree::new(&mut vec).unwrap();
+
+        let found = tree.nearest_k_search(&Point2WithId::new(0, 0.9, 0.), 3);
+        let ids = found.iter().map(|(p, _)| p.id).collect::<Vec<_>>();
+        assert_eq!(vec![2, 0, 1], ids);
+
+        assert_eq!(4, tree.nearest_k_search(&vec[0], 10).len());
+        assert_eq!(4, tree.nearest_k_search(&vec[0], usize::MAX).len());
+        assert!(tree.nearest_k_search(&vec[0], 0).is_empty());
+    }
+
+    type QcPoint3 = (f64, f64, f64);
 
+    quickcheck! {
+        fn tree_within_box_same_results_as_linear_3d(tree : Vec<(f64, f64, f64)>, corners: Vec<(QcPoint3, QcPoint3)>) -> bool {
+            if tree.is_empty() {
+                return true;
+            }
+
+            let point_vec = qc_value_vec_to_3d_points_vec(&tree);
+            for tree in trees_for_every_rule(&point_vec) {
+                for &(a, b) in &corners {
+                    let min = [a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)];
+                    let max = [a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)];
+
+                    let mut tree_result = tree.within_box(&min, &max);
+                    let mut linear_result = point_vec.iter().filter(|p| (0..3).all(|i| min[i] <= p.dims()[i] && p.dims()[i] <= max[i])).collect::<Vec<_>>();
+                    tree_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    linear_result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+                    assert_eq!(tree_result, linear_result, "testing box {:?} {:?}", min, max);
+                }
+            }
             true
         }
     }
 
+    #[test]
+    fn within_box_includes_boundaries() {
+        let mut vec = vec![
+            Point2WithId::new(0, 0., 0.),
+            Point2WithId::new(1, 1., 1.),
+            Point2WithId::new(2, 1., 2.),
+            Point2WithId::new(3, 2., 1.),
+        ];
+        let tree = Kdtree::new(&mut vec).unwrap();
+
+        let mut ids = tree
+            .within_box(&[0., 0.], &[1., 1.])
+            .iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(vec![0, 1], ids);
+        assert!(tree.within_box(&[3., 3.], &[4., 4.]).is_empty());
+    }
+
     #[test]
     fn has_neighbor_in_range() {
         let mut vec: Vec<Point2WithId> = vec![Point2WithId::new(0, 2., 0.)];
@@ -505,6 +898,81 @@ mod tests {
         assert_eq!(tree.nodes.len(), 2);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialized_tree_answers_queries_and_accepts_inserts() {
+        let mut vec = vec![
+            Point3WithId::new(0, 0., 0., 0.),
+            Point3WithId::new(1, 5., 1., 0.),
+            Point3WithId::new(2, -3., 2., 1.),
+        ];
+        let tree = Kdtree::from_vec(vec.clone()).unwrap();
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let mut restored: Kdtree<Point3WithId> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(tree.nodes.len(), restored.nodes.len());
+        assert_eq!(
+            tree.node_depth_during_last_rebuild,
+            restored.node_depth_during_last_rebuild
+        );
+        for p in &vec {
+            assert_eq!(p.id, restored.nearest_search(p).id);
+        }
+
+        let added = Point3WithId::new(3, 5., 1., 1.);
+        restored.insert_node(added);
+        vec.push(added);
+        for p in &vec {
+            assert_eq!(p.id, restored.nearest_search(p).id);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializing_rejects_broken_trees() {
+        let tree = Kdtree::from_vec(vec![[0., 0.], [1., 2.], [-3., 1.]]).unwrap();
+        let json = serde_json::to_value(&tree).unwrap();
+
+        let mut empty = json.clone();
+        empty["nodes"] = serde_json::json!([]);
+        assert!(serde_json::from_value::<Kdtree<[f64; 2]>>(empty).is_err());
+
+        let mut dangling = json.clone();
+        dangling["nodes"][0]["left_node"] = serde_json::json!(1000);
+        match serde_json::from_value::<Kdtree<[f64; 2]>>(dangling) {
+            Err(error) => assert!(error.to_string().contains("out of range")),
+            Ok(_) => panic!("accepted a child index out of range"),
+        }
+
+        let mut adding = json.clone();
+        adding["node_adding_dimension"] = serde_json::json!(2);
+        assert!(serde_json::from_value::<Kdtree<[f64; 2]>>(adding).is_err());
+
+        let mut settings = json;
+        settings["settings"]["rebuild_depth_factor"] = serde_json::json!(0.5);
+        assert!(serde_json::from_value::<Kdtree<[f64; 2]>>(settings).is_err());
+    }
+
+    const SPLIT_RULES: [SplitRule; 4] = [
+        SplitRule::SlidingMidpoint,
+        SplitRule::Midpoint,
+        SplitRule::Median,
+        SplitRule::MaxVariance,
+    ];
+
+    fn trees_for_every_rule<Point: KdtreePointTrait>(points: &[Point]) -> Vec<Kdtree<Point>> {
+        SPLIT_RULES
+            .iter()
+            .map(|rule| {
+                KdtreeBuilder::new()
+                    .split_rule(*rule)
+                    .build(&mut points.to_vec())
+                    .unwrap()
+            })
+            .collect()
+    }
+
     fn linear_nn<'a, Point: KdtreePointTrait>(nodes: &'a Vec<Point>, node: &'a Point) -> &'a Point {
         nodes
             .iter()
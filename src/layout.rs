@@ -0,0 +1,131 @@
+//! Cache friendly ordering of the `nodes` vector.
+//!
+//! `build_tree` stores nodes in depth first pre-order and `insert_node` appends them at the end,
+//! so a query jumping from a node to its far child or to a recently inserted node touches memory
+//! all over the vector. The van Emde Boas layout stores the top half of the tree's levels first,
+//! followed by each of the bottom half subtrees, recursively. Every subtree of any height then sits
+//! in a contiguous block, which keeps the cache lines touched by a root to leaf walk close together.
+
+use std::cmp;
+
+use kdtree::{Kdtree, KdtreeNode, KdtreePointTrait};
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Reorders the stored nodes into the van Emde Boas layout to speed up queries.
+    /// Query results do not change, inserts after this are appended at the end again.
+    /// `KdtreeBuilder::optimize_layout` builds trees that do this after every rebuild.
+    pub fn optimize_layout(&mut self) {
+        let height = self.height(0);
+        let mut order = Vec::with_capacity(self.nodes.len());
+        self.van_emde_boas_order(0, height, &mut order);
+
+        let mut new_index = vec![0usize; self.nodes.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+
+        let mut old_nodes: Vec<Option<KdtreeNode<KdtreePoint>>> =
+            self.nodes.drain(..).map(Some).collect();
+        for old in order {
+            let mut node = old_nodes[old].take().unwrap();
+            node.left_node = node.left_node.map(|i| new_index[i]);
+            node.right_node = node.right_node.map(|i| new_index[i]);
+            self.nodes.push(node);
+        }
+    }
+
+    fn height(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let left = node.left_node.map_or(0, |i| self.height(i));
+        let right = node.right_node.map_or(0, |i| self.height(i));
+
+        1 + cmp::max(left, right)
+    }
+
+    // Appends the nodes of the subtree at `root`, cut off below `height` levels, in van Emde Boas order.
+    fn van_emde_boas_order(&self, root: usize, height: usize, order: &mut Vec<usize>) {
+        if height == 1 {
+            order.push(root);
+            return;
+        }
+
+        let top_height = height / 2;
+        self.van_emde_boas_order(root, top_height, order);
+
+        let mut bottom_roots = vec![];
+        self.nodes_at_depth(root, top_height, &mut bottom_roots);
+        for bottom_root in bottom_roots {
+            self.van_emde_boas_order(bottom_root, height - top_height, order);
+        }
+    }
+
+    fn nodes_at_depth(&self, index: usize, depth: usize, found: &mut Vec<usize>) {
+        if depth == 0 {
+            found.push(index);
+            return;
+        }
+
+        let node = &self.nodes[index];
+        for child in node.left_node.iter().chain(node.right_node.iter()) {
+            self.nodes_at_depth(*child, depth - 1, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use builder::KdtreeBuilder;
+    use distance::squared_euclidean;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::Point2WithId;
+
+    fn grid(count: i32) -> Vec<Point2WithId> {
+        (0..count)
+            .map(|i| Point2WithId::new(i, f64::from(i % 8), f64::from(i / 8)))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_query_results_and_tree_structure() {
+        let mut tree = Kdtree::new(&mut grid(64)).unwrap();
+        for i in 0..20 {
+            tree.insert_node(Point2WithId::new(100 + i, f64::from(i) * 0.37, 3.5));
+        }
+        let mut optimized = tree.clone();
+        optimized.optimize_layout();
+
+        assert!(optimized.validate().is_ok());
+        assert_eq!(tree.len(), optimized.len());
+        for q in grid(80)
+            .iter()
+            .map(|p| Point2WithId::new(-1, p.dims()[0] + 0.3, p.dims()[1] - 0.2))
+        {
+            assert_eq!(tree.nearest_search(&q), optimized.nearest_search(&q));
+            assert_eq!(
+                tree.within(&q, 2., &squared_euclidean).len(),
+                optimized.within(&q, 2., &squared_euclidean).len()
+            );
+        }
+    }
+
+    #[test]
+    fn stores_top_levels_before_bottom_subtrees() {
+        let tree = KdtreeBuilder::new()
+            .optimize_layout(true)
+            .build(&mut grid(64))
+            .unwrap();
+        let top_height = tree.height(0) / 2;
+
+        let mut top = vec![];
+        for depth in 0..top_height {
+            tree.nodes_at_depth(0, depth, &mut top);
+        }
+        top.sort_unstable();
+        assert_eq!((0..top.len()).collect::<Vec<_>>(), top);
+
+        // the first bottom subtree follows right after, as one contiguous block.
+        let mut bottom_roots = vec![];
+        tree.nodes_at_depth(0, top_height, &mut bottom_roots);
+        assert_eq!(top.len(), bottom_roots[0]);
+    }
+}
@@ -1,7 +1,7 @@
 //! # Kdtree-Rust
 //!
 //! K-dimensional tree for Rust (sliding midpoint rule implemenation)
-//! Handles only 2D and 3D at the moment.
+//! Works in any number of dimensions.
 //!
 //! ## Usage
 //!
@@ -9,32 +9,13 @@
 //! use kdtree::Kdtree;
 //! use kdtree::distance::euclidean;
 //! use kdtree::distance::squared_euclidean;
-//! use kdtree::KdtreePointTrait;
-//!
-//!
-//! // This can by any object or point with its associated metadata
-//! #[derive(Copy, Clone, PartialEq)]
-//! pub struct Point3WithId {
-//!     dims: [f64; 3],
-//!     pub id: i32,
-//! }
-//!
-//!
-//! impl KdtreePointTrait for Point3WithId {
-//!     #[inline]
-//!     fn dims(&self) -> &[f64] {
-//!         return &self.dims;
-//!     }
-//! }
 //!
-//! let a = Point3WithId { dims: [0.0, 0.0, 0.0], id: 0};
-//! let b = Point3WithId { dims: [1.0, 0.0, 0.0], id: 1};
-//! let c = Point3WithId { dims: [1.0, 1.0, 0.0], id: 2};
-//! let d = Point3WithId { dims: [1.0, 1.0, 1.0], id: 3};
-//! let mut pts = vec![a,b,c,d];
+//! // Arrays, `Vec<f64>` and `(coordinates, id)` pairs can be used as points directly.
+//! let a = ([0.0, 0.0, 0.0], 0);
+//! let mut pts = vec![a, ([1.0, 0.0, 0.0], 1), ([1.0, 1.0, 0.0], 2), ([1.0, 1.0, 1.0], 3)];
 //! let tree = Kdtree::new(&mut pts).expect("tree creation failed, empty input vec?");
 //!
-//! assert_eq!(tree.nearest_search(&a).id, a.id);
+//! assert_eq!(tree.nearest_search(&a).1, 0);
 //!
 //! assert_eq!(tree.within(&a, 0.001, &euclidean).len(), 1);
 //! assert_eq!(tree.within(&a, 1.001, &euclidean).len(), 2);
@@ -44,6 +25,25 @@
 //! assert_eq!(tree.within(&a, 4.0, &squared_euclidean).len(), 4);
 //! ```
 //!
+//! Any other type can be stored by implementing `KdtreePointTrait` for it:
+//!
+//! ```
+//! use kdtree::KdtreePointTrait;
+//!
+//! #[derive(Clone, PartialEq)]
+//! pub struct Agent {
+//!     position: [f64; 2],
+//!     pub name: String,
+//! }
+//!
+//! impl KdtreePointTrait for Agent {
+//!     #[inline]
+//!     fn dims(&self) -> &[f64] {
+//!         &self.position
+//!     }
+//! }
+//! ```
+//!
 //!
 //! ## Notes
 //!
@@ -71,12 +71,61 @@ extern crate quickcheck;
 #[cfg(test)]
 extern crate rand;
 
+#[cfg(feature = "cgmath")]
+extern crate cgmath;
+#[cfg(feature = "glam")]
+extern crate glam;
+#[cfg(feature = "nalgebra")]
+extern crate nalgebra;
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+#[cfg(feature = "ndarray")]
+pub mod array;
+pub mod batch;
+pub mod binary;
 mod bounds;
+mod builder;
+mod concurrent;
+pub mod cosine;
 pub mod distance;
+mod error;
+pub mod geo;
+mod graph;
+#[cfg(any(feature = "glam", feature = "nalgebra", feature = "cgmath"))]
+pub mod integrations;
+pub mod iter;
+mod join;
 mod kdtree;
+mod layout;
+pub mod mahalanobis;
 mod partition;
+mod periodic;
+mod point_impls;
+pub mod pointcloud;
+#[cfg(target_arch = "x86_64")]
+mod simd;
+mod split;
+mod stats;
 pub mod test_common;
+mod validate;
+#[cfg(feature = "visualize")]
+mod visualize;
+mod weighted;
 
+pub use binary::KdtreeView;
+pub use builder::{KdtreeBuilder, TreeSettings};
+pub use concurrent::ConcurrentKdtree;
+pub use error::KdtreeError;
+pub use graph::KnnGraph;
 pub use kdtree::Kdtree;
 pub use kdtree::KdtreePointTrait;
+pub use split::SplitRule;
+pub use stats::{QueryStats, TreeStats};
 pub use test_common::{Point1WithId, Point2WithId, Point3WithId};
@@ -0,0 +1,297 @@
+//! Nearest neighbour queries under a Mahalanobis metric.
+//!
+//! The distance between `a` and `b` is `|W (a - b)|` for a whitening matrix `W`, or equivalently
+//! `sqrt((a - b)^T C^-1 (a - b))` for a covariance matrix `C = (W^T W)^-1`. The tree is built over
+//! whitened coordinates `W p`, where the metric becomes plain euclidean, and queries are whitened
+//! the same way before searching. Matrices are square, row major, with one row per dimension.
+//! Points and queries with another dimension count than the matrix fail with `DimensionMismatch`.
+
+use distance::squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+// relative difference up to which mirrored covariance entries count as equal,
+// computed covariances are often asymmetric in the last bits.
+const SYMMETRY_TOLERANCE: f64 = 1e-9;
+
+/// Point stored by a `MahalanobisTree`, `point` is the original point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhitenedPoint<P> {
+    dims: Vec<f64>,
+    pub point: P,
+}
+
+impl<P: Clone + PartialEq> KdtreePointTrait for WhitenedPoint<P> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+pub struct MahalanobisTree<P> {
+    tree: Kdtree<WhitenedPoint<P>>,
+    whitening: Vec<f64>,
+    dimensions: usize,
+}
+
+impl<P: KdtreePointTrait> MahalanobisTree<P> {
+    /// Builds a tree for the metric of a symmetric positive definite `covariance` matrix.
+    pub fn from_covariance(
+        points: Vec<P>,
+        covariance: &[f64],
+    ) -> Result<MahalanobisTree<P>, KdtreeError> {
+        let dimensions = dimensions_of(&points, covariance)?;
+        let whitening = inverse_cholesky(covariance, dimensions)?;
+
+        MahalanobisTree::from_whitening(points, &whitening)
+    }
+
+    /// Builds a tree for the metric `|whitening * (a - b)|`.
+    pub fn from_whitening(
+        points: Vec<P>,
+        whitening: &[f64],
+    ) -> Result<MahalanobisTree<P>, KdtreeError> {
+        let dimensions = dimensions_of(&points, whitening)?;
+        let whitening = whitening.to_vec();
+        let whitened = points
+            .into_iter()
+            .map(|point| {
+                check_dimensions(dimensions, point.dims())?;
+                Ok(WhitenedPoint {
+                    dims: whiten(&whitening, dimensions, point.dims()),
+                    point,
+                })
+            })
+            .collect::<Result<_, KdtreeError>>()?;
+        let tree = Kdtree::from_vec(whitened).ok_or(KdtreeError::EmptyInput)?;
+
+        Ok(MahalanobisTree {
+            tree,
+            whitening,
+            dimensions,
+        })
+    }
+
+    /// Underlying tree of whitened points.
+    pub fn tree(&self) -> &Kdtree<WhitenedPoint<P>> {
+        &self.tree
+    }
+
+    /// Coordinates of `dims` in the whitened space the tree is built over.
+    pub fn whiten(&self, dims: &[f64]) -> Result<Vec<f64>, KdtreeError> {
+        check_dimensions(self.dimensions, dims)?;
+
+        Ok(whiten(&self.whitening, self.dimensions, dims))
+    }
+
+    pub fn insert_node(&mut self, point: P) -> Result<(), KdtreeError> {
+        let dims = self.whiten(point.dims())?;
+        self.tree.insert_node(WhitenedPoint { dims, point });
+
+        Ok(())
+    }
+
+    /// Closest point to `query` and its Mahalanobis distance.
+    pub fn nearest(&self, query: &P) -> Result<(&P, f64), KdtreeError> {
+        let (nearest, squared_distance) = self
+            .tree
+            .nearest_k_search_by_dims(&self.whiten(query.dims())?, 1)[0];
+
+        Ok((&nearest.point, squared_distance.sqrt()))
+    }
+
+    /// Up to `k` closest points to `query` with their Mahalanobis distances, nearest first.
+    pub fn nearest_k(&self, query: &P, k: usize) -> Result<Vec<(&P, f64)>, KdtreeError> {
+        Ok(self
+            .tree
+            .nearest_k_search_by_dims(&self.whiten(query.dims())?, k)
+            .into_iter()
+            .map(|(p, squared_distance)| (&p.point, squared_distance.sqrt()))
+            .collect())
+    }
+
+    /// Points within Mahalanobis distance `radius` of `query`, none for a negative or NaN radius.
+    pub fn within(&self, query: &P, radius: f64) -> Result<Vec<&P>, KdtreeError> {
+        let query = WhitenedPoint {
+            dims: self.whiten(query.dims())?,
+            point: query.clone(),
+        };
+        // a negative radius would square to a positive one.
+        if radius.is_nan() || radius < 0. {
+            return Ok(vec![]);
+        }
+
+        Ok(self
+            .tree
+            .within(&query, radius * radius, &squared_euclidean)
+            .into_iter()
+            .map(|p| &p.point)
+            .collect())
+    }
+}
+
+fn dimensions_of<P: KdtreePointTrait>(points: &[P], matrix: &[f64]) -> Result<usize, KdtreeError> {
+    let dimensions = points.first().ok_or(KdtreeError::EmptyInput)?.dims().len();
+    if matrix.len() != dimensions * dimensions {
+        return Err(KdtreeError::DimensionMismatch {
+            expected: dimensions * dimensions,
+            actual: matrix.len(),
+        });
+    }
+
+    Ok(dimensions)
+}
+
+fn check_dimensions(dimensions: usize, dims: &[f64]) -> Result<(), KdtreeError> {
+    if dims.len() != dimensions {
+        return Err(KdtreeError::DimensionMismatch {
+            expected: dimensions,
+            actual: dims.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn whiten(whitening: &[f64], dimensions: usize, dims: &[f64]) -> Vec<f64> {
+    whitening
+        .chunks(dimensions)
+        .map(|row| row.iter().zip(dims).map(|(w, x)| w * x).sum())
+        .collect()
+}
+
+// Inverse of the lower triangular Cholesky factor `L` of `covariance = L L^T`,
+// which whitens since `|L^-1 x|^2 = x^T covariance^-1 x`.
+fn inverse_cholesky(covariance: &[f64], n: usize) -> Result<Vec<f64>, KdtreeError> {
+    let mut l = vec![0.; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let (a, b) = (covariance[i * n + j], covariance[j * n + i]);
+            if (a - b).abs() > SYMMETRY_TOLERANCE * a.abs().max(b.abs()) {
+                return Err(KdtreeError::InvalidMetric(
+                    "covariance matrix is not symmetric".to_string(),
+                ));
+            }
+
+            let sum: f64 = (0..j).map(|k| l[i * n + k] * l[j * n + k]).sum();
+            if i == j {
+                let diagonal = covariance[i * n + i] - sum;
+                if diagonal <= 0. || diagonal.is_nan() {
+                    return Err(KdtreeError::InvalidMetric(
+                        "covariance matrix is not positive definite".to_string(),
+                    ));
+                }
+                l[i * n + i] = diagonal.sqrt();
+            } else {
+                l[i * n + j] = (covariance[i * n + j] - sum) / l[j * n + j];
+            }
+        }
+    }
+
+    // forward substitution of L X = I, one column of X at a time.
+    let mut inverse = vec![0.; n * n];
+    for column in 0..n {
+        for i in column..n {
+            let identity = if i == column { 1. } else { 0. };
+            let sum: f64 = (column..i)
+                .map(|k| l[i * n + k] * inverse[k * n + column])
+                .sum();
+            inverse[i * n + column] = (identity - sum) / l[i * n + i];
+        }
+    }
+
+    Ok(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::Point2WithId;
+
+    fn mahalanobis(a: &[f64], b: &[f64], inverse_covariance: &[f64]) -> f64 {
+        let d = [a[0] - b[0], a[1] - b[1]];
+        let c = inverse_covariance;
+        (d[0] * (c[0] * d[0] + c[1] * d[1]) + d[1] * (c[2] * d[0] + c[3] * d[1])).sqrt()
+    }
+
+    #[test]
+    fn covariance_gives_mahalanobis_distances() {
+        // covariance [[4, 2], [2, 3]] has the inverse [[3, -2], [-2, 4]] / 8.
+        let covariance = [4., 2., 2., 3.];
+        let inverse = [3. / 8., -2. / 8., -2. / 8., 4. / 8.];
+        let points: Vec<_> = (0..50)
+            .map(|i| Point2WithId::new(i, f64::from(i % 7) * 1.3, f64::from(i / 7) * 0.9))
+            .collect();
+        let tree = MahalanobisTree::from_covariance(points.clone(), &covariance).unwrap();
+
+        for q in (0..20).map(|i| Point2WithId::new(-1, f64::from(i) * 0.41, f64::from(i % 5) * 1.1))
+        {
+            let linear = points
+                .iter()
+                .map(|p| mahalanobis(q.dims(), p.dims(), &inverse))
+                .fold(f64::INFINITY, f64::min);
+            let (nearest, distance) = tree.nearest(&q).unwrap();
+            assert!((linear - distance).abs() < 1e-9);
+            assert!((linear - mahalanobis(q.dims(), nearest.dims(), &inverse)).abs() < 1e-9);
+
+            let mut found: Vec<i32> = tree.within(&q, 1.5).unwrap().iter().map(|p| p.id).collect();
+            found.sort_unstable();
+            let expected: Vec<i32> = points
+                .iter()
+                .filter(|p| mahalanobis(q.dims(), p.dims(), &inverse) <= 1.5)
+                .map(|p| p.id)
+                .collect();
+            assert_eq!(expected, found);
+        }
+    }
+
+    #[test]
+    fn rejects_unusable_matrices() {
+        let points = vec![Point2WithId::new(0, 0., 0.)];
+
+        match MahalanobisTree::from_covariance(points.clone(), &[1., 2., 2., 1.]) {
+            Err(KdtreeError::InvalidMetric(_)) => {}
+            _ => panic!("accepted a covariance that is not positive definite"),
+        }
+        match MahalanobisTree::from_covariance(points.clone(), &[4., 2., 2. + 1e-6, 3.]) {
+            Err(KdtreeError::InvalidMetric(_)) => {}
+            _ => panic!("accepted a covariance that is not symmetric"),
+        }
+        match MahalanobisTree::from_whitening(points, &[1., 0., 0.]) {
+            Err(KdtreeError::DimensionMismatch { expected, actual }) => {
+                assert_eq!((4, 3), (expected, actual))
+            }
+            _ => panic!("accepted a matrix of the wrong size"),
+        }
+    }
+
+    #[test]
+    fn checks_dimensions_of_points_and_queries() {
+        let identity = [1., 0., 0., 1.];
+        let mixed = vec![vec![0., 0.], vec![1., 1., 1.]];
+        match MahalanobisTree::from_whitening(mixed, &identity) {
+            Err(KdtreeError::DimensionMismatch { expected, actual }) => {
+                assert_eq!((2, 3), (expected, actual))
+            }
+            _ => panic!("accepted a point of the wrong dimension"),
+        }
+
+        let mut tree = MahalanobisTree::from_whitening(vec![vec![0., 0.]], &identity).unwrap();
+        assert!(tree.nearest(&vec![0.]).is_err());
+        assert!(tree.nearest_k(&vec![0., 0., 0.], 1).is_err());
+        assert!(tree.within(&vec![0.], 1.).is_err());
+        assert!(tree.insert_node(vec![1.]).is_err());
+        assert!(tree.within(&vec![0., 0.], -1.).unwrap().is_empty());
+        assert_eq!(1, tree.nearest_k(&vec![0., 0.], 5).unwrap().len());
+    }
+
+    #[test]
+    fn accepts_covariances_asymmetric_by_rounding() {
+        let covariance = [4., 0.1 + 0.2, 0.3, 3.];
+        assert!(covariance[1] != covariance[2]);
+
+        let tree =
+            MahalanobisTree::from_covariance(vec![Point2WithId::new(0, 0., 0.)], &covariance);
+        assert!(tree.is_ok());
+    }
+}
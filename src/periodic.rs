@@ -0,0 +1,282 @@
+//! Queries on periodic (toroidal) spaces, where coordinates wrap around at the edges.
+//!
+//! `periods` holds one period per dimension, a period of `0.` leaves that dimension unwrapped.
+//! Distances use the minimum image convention: along a wrapped dimension two coordinates are as far
+//! apart as their closest images. Pruning checks the wrapped distance to each node's cell, so queries
+//! near an edge find points close to the opposite edge. Points should lie within one period, e.g. `[0, period)`.
+//!
+//! # Panics
+//!
+//! Queries panic unless `periods` has one entry per dimension of the query point.
+
+use distance::periodic_squared_euclidean;
+use kdtree::{Kdtree, KdtreeNode, KdtreePointTrait};
+
+type Child = Option<(usize, f64, (f64, f64))>;
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Nearest point to `node` under periodic boundaries.
+    pub fn nearest_search_periodic(&self, node: &KdtreePoint, periods: &[f64]) -> KdtreePoint {
+        let dims = node.dims();
+        check_periods(dims, periods);
+
+        let mut cell = self.bounding_box.clone();
+        let mut best = (0usize, f64::INFINITY);
+        self.nearest_search_periodic_impl(dims, periods, 0usize, &mut cell, &mut best);
+
+        self.nodes[best.0].point.clone()
+    }
+
+    /// Points whose periodic euclidean distance to `node` is at most `radius`,
+    /// none for a negative or NaN radius.
+    pub fn within_periodic(
+        &self,
+        node: &KdtreePoint,
+        radius: f64,
+        periods: &[f64],
+    ) -> Vec<&KdtreePoint> {
+        let dims = node.dims();
+        check_periods(dims, periods);
+        // a negative radius would square to a positive one.
+        if radius.is_nan() || radius < 0. {
+            return vec![];
+        }
+
+        let mut cell = self.bounding_box.clone();
+        let mut found = vec![];
+        self.within_periodic_impl(
+            dims,
+            periods,
+            radius * radius,
+            0usize,
+            &mut cell,
+            &mut found,
+        );
+
+        found.into_iter().map(|x| &self.nodes[x].point).collect()
+    }
+
+    fn nearest_search_periodic_impl(
+        &self,
+        dims: &[f64],
+        periods: &[f64],
+        searched_index: usize,
+        cell: &mut [(f64, f64)],
+        best: &mut (usize, f64),
+    ) {
+        let node = &self.nodes[searched_index];
+
+        let distance = periodic_squared_euclidean(dims, node.point.dims(), periods);
+        if distance < best.1 {
+            *best = (searched_index, distance);
+        }
+
+        for &(child, lower_bound, extent) in self
+            .children_by_distance(dims, periods, node, cell)
+            .iter()
+            .flatten()
+        {
+            if lower_bound < best.1 {
+                let d = node.dimension;
+                let saved = cell[d];
+                cell[d] = extent;
+                self.nearest_search_periodic_impl(dims, periods, child, cell, best);
+                cell[d] = saved;
+            }
+        }
+    }
+
+    fn within_periodic_impl(
+        &self,
+        dims: &[f64],
+        periods: &[f64],
+        squared_radius: f64,
+        searched_index: usize,
+        cell: &mut [(f64, f64)],
+        found: &mut Vec<usize>,
+    ) {
+        let node = &self.nodes[searched_index];
+
+        if periodic_squared_euclidean(dims, node.point.dims(), periods) <= squared_radius {
+            found.push(searched_index);
+        }
+
+        for &(child, lower_bound, extent) in self
+            .children_by_distance(dims, periods, node, cell)
+            .iter()
+            .flatten()
+        {
+            if lower_bound <= squared_radius {
+                let d = node.dimension;
+                let saved = cell[d];
+                cell[d] = extent;
+                self.within_periodic_impl(dims, periods, squared_radius, child, cell, found);
+                cell[d] = saved;
+            }
+        }
+    }
+
+    // Children of `node` with the squared periodic distance from `dims` to their cell and
+    // the cell's extent along the splitting dimension, closest child first.
+    fn children_by_distance(
+        &self,
+        dims: &[f64],
+        periods: &[f64],
+        node: &KdtreeNode<KdtreePoint>,
+        cell: &mut [(f64, f64)],
+    ) -> [Child; 2] {
+        let d = node.dimension;
+        let saved = cell[d];
+        let mut child_with_distance = |child: Option<usize>, extent: (f64, f64)| {
+            child.map(|child| {
+                cell[d] = extent;
+                let distance = squared_distance_to_cell(dims, periods, cell);
+                cell[d] = saved;
+                (child, distance, extent)
+            })
+        };
+
+        let left = child_with_distance(node.left_node, (saved.0, node.split_on));
+        let right = child_with_distance(node.right_node, (node.split_on, saved.1));
+        match (left, right) {
+            (Some(l), Some(r)) if r.1 < l.1 => [right, left],
+            _ => [left, right],
+        }
+    }
+}
+
+fn check_periods(dims: &[f64], periods: &[f64]) {
+    assert_eq!(
+        dims.len(),
+        periods.len(),
+        "one period per dimension is needed"
+    );
+}
+
+fn squared_distance_to_cell(dims: &[f64], periods: &[f64], cell: &[(f64, f64)]) -> f64 {
+    dims.iter()
+        .zip(periods)
+        .zip(cell)
+        .map(|((&x, &period), &(min, max))| {
+            let d = distance_to_interval(x, min, max, period);
+            d * d
+        })
+        .sum()
+}
+
+// Distance from `x` to the closest image of `[min, max]`.
+fn distance_to_interval(x: f64, min: f64, max: f64, period: f64) -> f64 {
+    if min <= x && x <= max {
+        return 0.;
+    }
+    if period <= 0. {
+        return (min - x).max(x - max);
+    }
+    let width = max - min;
+    if width >= period {
+        return 0.;
+    }
+
+    let past_min = (x - min).rem_euclid(period);
+    if past_min <= width {
+        0.
+    } else {
+        (period - past_min).min(past_min - width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::periodic_squared_euclidean;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::Point2WithId;
+
+    const PERIODS: [f64; 2] = [10., 0.];
+
+    fn points() -> Vec<Point2WithId> {
+        // a deterministic scatter over [0, 10) x [0, 10).
+        (0..200)
+            .map(|i| {
+                let x = f64::from((i * 37) % 200) / 20.;
+                let y = f64::from((i * 71) % 200) / 20.;
+                Point2WithId::new(i, x, y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_neighbours_across_the_boundary() {
+        let tree = Kdtree::from_vec(vec![
+            Point2WithId::new(0, 0.1, 5.),
+            Point2WithId::new(1, 5., 5.),
+            Point2WithId::new(2, 8., 5.),
+        ])
+        .unwrap();
+        let query = Point2WithId::new(-1, 9.8, 5.);
+
+        assert_eq!(2, tree.nearest_search(&query).id);
+        assert_eq!(0, tree.nearest_search_periodic(&query, &PERIODS).id);
+        assert_eq!(1, tree.within_periodic(&query, 0.5, &PERIODS).len());
+        // y is not wrapped.
+        let query = Point2WithId::new(-1, 5., 9.8);
+        assert_eq!(1, tree.nearest_search_periodic(&query, &PERIODS).id);
+    }
+
+    #[test]
+    fn negative_or_nan_radius_finds_nothing() {
+        let tree = Kdtree::from_vec(vec![[0.5, 0.], [9.5, 0.]]).unwrap();
+
+        assert_eq!(2, tree.within_periodic(&[0., 0.], 1., &[10., 10.]).len());
+        assert!(tree.within_periodic(&[0., 0.], -1., &[10., 10.]).is_empty());
+        assert!(tree
+            .within_periodic(&[0., 0.], f64::NAN, &[10., 10.])
+            .is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "one period per dimension")]
+    fn periods_must_match_the_dimensions() {
+        let tree = Kdtree::from_vec(vec![[0.5, 0.], [9.5, 0.]]).unwrap();
+
+        tree.within_periodic(&[0., 0.], 1., &[10.]);
+    }
+
+    #[test]
+    fn inserted_points_outside_the_built_box_are_found() {
+        let mut tree = Kdtree::from_vec(vec![[4., 4.], [5., 5.]]).unwrap();
+        tree.insert_node([9.9, 5.]);
+
+        assert_eq!([9.9, 5.], tree.nearest_search_periodic(&[0., 5.], &PERIODS));
+        assert_eq!(1, tree.within_periodic(&[0., 5.], 0.5, &PERIODS).len());
+    }
+
+    #[test]
+    fn same_results_as_linear_search() {
+        let points = points();
+        let tree = Kdtree::from_vec(points.clone()).unwrap();
+
+        for q in
+            (0..50).map(|i| Point2WithId::new(-1, f64::from(i) * 0.199, f64::from(i % 7) * 1.43))
+        {
+            let distance =
+                |p: &Point2WithId| periodic_squared_euclidean(q.dims(), p.dims(), &PERIODS);
+
+            let nearest = tree.nearest_search_periodic(&q, &PERIODS);
+            let linear = points.iter().map(&distance).fold(f64::INFINITY, f64::min);
+            assert_eq!(linear, distance(&nearest));
+
+            let mut found: Vec<i32> = tree
+                .within_periodic(&q, 1.2, &PERIODS)
+                .iter()
+                .map(|p| p.id)
+                .collect();
+            found.sort_unstable();
+            let linear: Vec<i32> = points
+                .iter()
+                .filter(|p| distance(p) <= 1.2 * 1.2)
+                .map(|p| p.id)
+                .collect();
+            assert_eq!(linear, found);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Ready made `KdtreePointTrait` implementations, so plain coordinate containers
+//! can be put into the tree without writing a wrapper type first.
+//!
+//! Tuples such as `(f64, f64)` are not covered: Rust does not guarantee their
+//! fields are laid out contiguously, so they cannot be viewed as `&[f64]`.
+
+use kdtree::KdtreePointTrait;
+
+impl<const N: usize> KdtreePointTrait for [f64; N] {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        self
+    }
+}
+
+impl KdtreePointTrait for Vec<f64> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        self
+    }
+}
+
+impl KdtreePointTrait for Box<[f64]> {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        self
+    }
+}
+
+/// Coordinates paired with an identifier or any other payload, e.g. `([1.0, 2.0], 42)`.
+/// Only the coordinates take part in the search.
+impl<Coords: KdtreePointTrait, Id: Clone + PartialEq> KdtreePointTrait for (Coords, Id) {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        self.0.dims()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance;
+    use crate::Kdtree;
+
+    #[test]
+    fn arrays_can_be_used_directly() {
+        let mut points = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+        let tree = Kdtree::new(&mut points).unwrap();
+
+        assert_eq!([1.0, 1.0], tree.nearest_search(&[0.9, 0.8]));
+    }
+
+    #[test]
+    fn vec_and_boxed_slices_can_be_used_directly() {
+        let mut points = vec![vec![0.0, 0.0, 0.0], vec![1.0, 2.0, 3.0]];
+        let tree = Kdtree::new(&mut points).unwrap();
+        assert_eq!(vec![1.0, 2.0, 3.0], tree.nearest_search(&vec![1.0, 1.5, 3.0]));
+
+        let mut points: Vec<Box<[f64]>> = vec![Box::new([0.0]), Box::new([5.0])];
+        let tree = Kdtree::new(&mut points).unwrap();
+        assert_eq!(5.0, tree.nearest_search(&(Box::new([4.0]) as Box<[f64]>))[0]);
+    }
+
+    #[test]
+    fn points_of_more_than_three_dimensions() {
+        let mut points = vec![[0.0; 5], [1.0, 2.0, 3.0, 4.0, 5.0], [1.0, 2.0, 3.0, 4.0, -5.0]];
+        let mut tree = Kdtree::new(&mut points).unwrap();
+        tree.insert_node([0.0, 0.0, 0.0, 0.0, 9.0]);
+
+        assert_eq!([1.0, 2.0, 3.0, 4.0, 5.0], tree.nearest_search(&[1.0, 2.0, 3.0, 4.0, 4.0]));
+        assert_eq!([0.0, 0.0, 0.0, 0.0, 9.0], tree.nearest_search(&[0.0, 0.0, 0.0, 0.0, 8.0]));
+
+        let mut points = vec![vec![0.0; 6], vec![1.0; 6], vec![-1.0; 6]];
+        let tree = Kdtree::new(&mut points).unwrap();
+        assert_eq!(vec![-1.0; 6], tree.nearest_search(&vec![-0.8; 6]));
+        assert_eq!(2, tree.within(&vec![0.5; 6], 1.5, &distance::euclidean).len());
+    }
+
+    #[test]
+    fn coordinates_with_id_pairs_search_on_coordinates_only() {
+        let mut points = vec![([0.0, 0.0], "origin"), ([3.0, 4.0], "far")];
+        let tree = Kdtree::new(&mut points).unwrap();
+
+        assert_eq!("far", tree.nearest_search(&([2.5, 4.0], "")).1);
+        assert_eq!(1, tree.within(&([0.0, 0.0], ""), 1.0, &distance::euclidean).len());
+    }
+}
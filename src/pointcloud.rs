@@ -0,0 +1,728 @@
+//! Loading point clouds from ASCII XYZ, CSV and PLY (ASCII or binary little endian) files.
+//!
+//! Columns or properties besides the coordinates, such as intensity or colour,
+//! are kept as numeric payload next to each point.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+/// Point read from a cloud, `index` refers to its row in the `PointCloud` it came from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CloudPoint {
+    dims: [f64; 3],
+    pub index: usize,
+}
+
+impl CloudPoint {
+    pub fn new(index: usize, x: f64, y: f64, z: f64) -> CloudPoint {
+        CloudPoint {
+            dims: [x, y, z],
+            index,
+        }
+    }
+}
+
+impl KdtreePointTrait for CloudPoint {
+    #[inline]
+    fn dims(&self) -> &[f64] {
+        &self.dims
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+    pub points: Vec<CloudPoint>,
+    payload_names: Vec<String>,
+    payload: Vec<f64>,
+}
+
+impl PointCloud {
+    /// Reads a file picking the format by its extension: `xyz`, `csv` (with default `CsvOptions`) or `ply`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<PointCloud, KdtreeError> {
+        PointCloud::open_with_csv_options(path, &CsvOptions::default())
+    }
+
+    /// `open` reading csv files with `csv_options`.
+    pub fn open_with_csv_options<P: AsRef<Path>>(
+        path: P,
+        csv_options: &CsvOptions,
+    ) -> Result<PointCloud, KdtreeError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let reader = BufReader::new(File::open(path)?);
+
+        match extension.as_deref() {
+            Some("xyz") | Some("txt") => read_xyz(reader),
+            Some("csv") => read_csv(reader, csv_options),
+            Some("ply") => read_ply(reader),
+            _ => Err(KdtreeError::InvalidFormat(format!(
+                "can not tell the point cloud format of {}",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Names of the payload columns, in the order `payload` returns their values.
+    pub fn payload_names(&self) -> &[String] {
+        &self.payload_names
+    }
+
+    /// Payload values of the point with the given `index`.
+    pub fn payload(&self, index: usize) -> &[f64] {
+        let width = self.payload_names.len();
+        &self.payload[index * width..(index + 1) * width]
+    }
+
+    pub fn build_tree(&self) -> Result<Kdtree<CloudPoint>, KdtreeError> {
+        Kdtree::from_vec(self.points.clone()).ok_or(KdtreeError::EmptyInput)
+    }
+
+    fn push<I: IntoIterator<Item = f64>>(&mut self, x: f64, y: f64, z: f64, payload: I) {
+        let index = self.points.len();
+        self.points.push(CloudPoint::new(index, x, y, z));
+        self.payload.extend(payload);
+    }
+}
+
+/// Reads whitespace separated `x y z [payload...]` lines. Empty lines and lines starting with `#` are skipped.
+pub fn read_xyz<R: BufRead>(reader: R) -> Result<PointCloud, KdtreeError> {
+    let mut cloud = PointCloud::default();
+    let mut column_count = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match column_count {
+            None => {
+                if fields.len() < 3 {
+                    return Err(parse_error(
+                        line_number,
+                        format!("expected at least 3 columns, got {}", fields.len()),
+                    ));
+                }
+                column_count = Some(fields.len());
+                cloud.payload_names = (3..fields.len()).map(|i| format!("column{}", i)).collect();
+            }
+            Some(count) if count != fields.len() => {
+                return Err(parse_error(
+                    line_number,
+                    format!("expected {} columns, got {}", count, fields.len()),
+                ));
+            }
+            _ => {}
+        }
+
+        let x = parse_field(fields[0], line_number)?;
+        let y = parse_field(fields[1], line_number)?;
+        let z = parse_field(fields[2], line_number)?;
+        let payload = fields[3..]
+            .iter()
+            .map(|f| parse_field(f, line_number))
+            .collect::<Result<Vec<_>, _>>()?;
+        cloud.push(x, y, z, payload);
+    }
+
+    Ok(cloud)
+}
+
+/// Layout of a CSV file, columns are counted from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    /// Whether the first line holds column names, which are then used as payload names.
+    pub has_header: bool,
+    pub x_column: usize,
+    pub y_column: usize,
+    pub z_column: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: ',',
+            has_header: true,
+            x_column: 0,
+            y_column: 1,
+            z_column: 2,
+        }
+    }
+}
+
+/// Reads delimiter separated values, every column besides the coordinate ones becomes payload.
+/// Fields are trimmed, quoting is not supported.
+pub fn read_csv<R: BufRead>(reader: R, options: &CsvOptions) -> Result<PointCloud, KdtreeError> {
+    let coordinate_columns = [options.x_column, options.y_column, options.z_column];
+    let mut cloud = PointCloud::default();
+    let mut column_count = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(options.delimiter).map(|f| f.trim()).collect();
+        match column_count {
+            None => {
+                if let Some(&missing) = coordinate_columns.iter().find(|&&c| c >= fields.len()) {
+                    return Err(parse_error(
+                        line_number,
+                        format!(
+                            "no column {} in a line of {} columns",
+                            missing,
+                            fields.len()
+                        ),
+                    ));
+                }
+                column_count = Some(fields.len());
+                cloud.payload_names = (0..fields.len())
+                    .filter(|i| !coordinate_columns.contains(i))
+                    .map(|i| {
+                        if options.has_header {
+                            fields[i].to_string()
+                        } else {
+                            format!("column{}", i)
+                        }
+                    })
+                    .collect();
+
+                if options.has_header {
+                    continue;
+                }
+            }
+            Some(count) if count != fields.len() => {
+                return Err(parse_error(
+                    line_number,
+                    format!("expected {} columns, got {}", count, fields.len()),
+                ));
+            }
+            _ => {}
+        }
+
+        let x = parse_field(fields[options.x_column], line_number)?;
+        let y = parse_field(fields[options.y_column], line_number)?;
+        let z = parse_field(fields[options.z_column], line_number)?;
+        let payload = fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !coordinate_columns.contains(i))
+            .map(|(_, f)| parse_field(f, line_number))
+            .collect::<Result<Vec<_>, _>>()?;
+        cloud.push(x, y, z, payload);
+    }
+
+    Ok(cloud)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum PlyScalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyScalar {
+    fn parse(name: &str) -> Option<PlyScalar> {
+        match name {
+            "char" | "int8" => Some(PlyScalar::Int8),
+            "uchar" | "uint8" => Some(PlyScalar::UInt8),
+            "short" | "int16" => Some(PlyScalar::Int16),
+            "ushort" | "uint16" => Some(PlyScalar::UInt16),
+            "int" | "int32" => Some(PlyScalar::Int32),
+            "uint" | "uint32" => Some(PlyScalar::UInt32),
+            "float" | "float32" => Some(PlyScalar::Float32),
+            "double" | "float64" => Some(PlyScalar::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyScalar::Int8 | PlyScalar::UInt8 => 1,
+            PlyScalar::Int16 | PlyScalar::UInt16 => 2,
+            PlyScalar::Int32 | PlyScalar::UInt32 | PlyScalar::Float32 => 4,
+            PlyScalar::Float64 => 8,
+        }
+    }
+
+    fn read_le(self, bytes: &[u8]) -> f64 {
+        match self {
+            PlyScalar::Int8 => f64::from(bytes[0] as i8),
+            PlyScalar::UInt8 => f64::from(bytes[0]),
+            PlyScalar::Int16 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            PlyScalar::UInt16 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            PlyScalar::Int32 => {
+                f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::UInt32 => {
+                f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::Float32 => {
+                f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::Float64 => {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(buf)
+            }
+        }
+    }
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    // `None` marks a list property, which only elements other than `vertex` may have.
+    properties: Vec<(String, Option<PlyScalar>)>,
+}
+
+/// Reads the `vertex` element of a PLY file in `ascii` or `binary_little_endian` format.
+/// Vertex properties other than `x`, `y` and `z` become payload, other elements are skipped.
+pub fn read_ply<R: BufRead>(mut reader: R) -> Result<PointCloud, KdtreeError> {
+    let mut line_number = 0;
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+
+    loop {
+        let mut raw = vec![];
+        if reader.read_until(b'\n', &mut raw)? == 0 {
+            return Err(parse_error(
+                line_number,
+                "header ends before end_header".to_string(),
+            ));
+        }
+        line_number += 1;
+        let line = String::from_utf8_lossy(&raw);
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if words != ["ply"] {
+                return Err(parse_error(
+                    line_number,
+                    "missing ply signature".to_string(),
+                ));
+            }
+            continue;
+        }
+
+        match words.as_slice() {
+            ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", other, _] => {
+                return Err(parse_error(
+                    line_number,
+                    format!("unsupported format {}", other),
+                ))
+            }
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| {
+                    parse_error(line_number, format!("bad element count {}", count))
+                })?;
+                elements.push(PlyElement {
+                    name: name.to_string(),
+                    count,
+                    properties: vec![],
+                });
+            }
+            ["property", "list", _, _, name] => match elements.last_mut() {
+                Some(element) => element.properties.push((name.to_string(), None)),
+                None => {
+                    return Err(parse_error(
+                        line_number,
+                        "property outside of an element".to_string(),
+                    ))
+                }
+            },
+            ["property", kind, name] => {
+                let kind = PlyScalar::parse(kind).ok_or_else(|| {
+                    parse_error(line_number, format!("unknown property type {}", kind))
+                })?;
+                match elements.last_mut() {
+                    Some(element) => element.properties.push((name.to_string(), Some(kind))),
+                    None => {
+                        return Err(parse_error(
+                            line_number,
+                            "property outside of an element".to_string(),
+                        ))
+                    }
+                }
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => {
+                return Err(parse_error(
+                    line_number,
+                    format!("unexpected header line {}", line.trim()),
+                ))
+            }
+        }
+    }
+
+    let format =
+        format.ok_or_else(|| parse_error(line_number, "header has no format line".to_string()))?;
+    let vertex_position = elements
+        .iter()
+        .position(|e| e.name == "vertex")
+        .ok_or_else(|| parse_error(line_number, "header has no vertex element".to_string()))?;
+
+    let vertex = &elements[vertex_position];
+    let mut kinds = Vec::with_capacity(vertex.properties.len());
+    for (name, kind) in &vertex.properties {
+        match kind {
+            Some(kind) => kinds.push(*kind),
+            None => {
+                return Err(KdtreeError::InvalidFormat(format!(
+                    "list property {} in vertex element is not supported",
+                    name
+                )))
+            }
+        }
+    }
+    let column = |wanted: &str| {
+        vertex
+            .properties
+            .iter()
+            .position(|(name, _)| name == wanted)
+            .ok_or_else(|| {
+                KdtreeError::InvalidFormat(format!("vertex element has no {} property", wanted))
+            })
+    };
+    let coordinate_columns = [column("x")?, column("y")?, column("z")?];
+
+    let mut cloud = PointCloud {
+        payload_names: vertex
+            .properties
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !coordinate_columns.contains(i))
+            .map(|(_, (name, _))| name.clone())
+            .collect(),
+        ..PointCloud::default()
+    };
+
+    let mut values = vec![0.; kinds.len()];
+    match format {
+        PlyFormat::Ascii => {
+            let mut lines = reader.lines();
+            let skipped_lines = elements[..vertex_position]
+                .iter()
+                .try_fold(0usize, |total, e| total.checked_add(e.count))
+                .ok_or_else(|| KdtreeError::InvalidFormat("element counts overflow".to_string()))?;
+            for _ in 0..skipped_lines {
+                line_number += 1;
+                if lines.next().transpose()?.is_none() {
+                    return Err(parse_error(
+                        line_number,
+                        "file ends before the vertex data".to_string(),
+                    ));
+                }
+            }
+
+            for _ in 0..vertex.count {
+                line_number += 1;
+                let line = lines.next().transpose()?.ok_or_else(|| {
+                    parse_error(
+                        line_number,
+                        "file ends before all vertices were read".to_string(),
+                    )
+                })?;
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() != kinds.len() {
+                    return Err(parse_error(
+                        line_number,
+                        format!(
+                            "expected {} vertex values, got {}",
+                            kinds.len(),
+                            fields.len()
+                        ),
+                    ));
+                }
+                for (value, field) in values.iter_mut().zip(fields) {
+                    *value = parse_field(field, line_number)?;
+                }
+                push_vertex(&mut cloud, &values, &coordinate_columns);
+            }
+        }
+        PlyFormat::BinaryLittleEndian => {
+            for element in &elements[..vertex_position] {
+                let mut size = 0usize;
+                for (name, kind) in &element.properties {
+                    match kind {
+                        Some(kind) => {
+                            size = size
+                                .checked_add(kind.size())
+                                .ok_or_else(|| overflow(element))?
+                        }
+                        None => {
+                            return Err(KdtreeError::InvalidFormat(format!(
+                            "can not skip list property {} of element {} preceding the vertices",
+                            name, element.name
+                        )))
+                        }
+                    }
+                }
+                // streamed rather than read into a buffer, the count comes from an untrusted header.
+                let skipped = size
+                    .checked_mul(element.count)
+                    .ok_or_else(|| overflow(element))? as u64;
+                if io::copy(&mut (&mut reader).take(skipped), &mut io::sink())? < skipped {
+                    return Err(KdtreeError::InvalidFormat(format!(
+                        "binary data ends inside the {} element",
+                        element.name
+                    )));
+                }
+            }
+
+            let mut record = vec![0u8; kinds.iter().map(|k| k.size()).sum()];
+            for _ in 0..vertex.count {
+                read_binary(&mut reader, &mut record, "vertex")?;
+                let mut offset = 0;
+                for (value, kind) in values.iter_mut().zip(&kinds) {
+                    *value = kind.read_le(&record[offset..]);
+                    offset += kind.size();
+                }
+                if values.iter().any(|v| !v.is_finite()) {
+                    return Err(KdtreeError::InvalidFormat(format!(
+                        "vertex {} has a value that is not a finite number",
+                        cloud.len()
+                    )));
+                }
+                push_vertex(&mut cloud, &values, &coordinate_columns);
+            }
+        }
+    }
+
+    Ok(cloud)
+}
+
+fn push_vertex(cloud: &mut PointCloud, values: &[f64], coordinate_columns: &[usize; 3]) {
+    let payload = values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !coordinate_columns.contains(i))
+        .map(|(_, v)| *v);
+    cloud.push(
+        values[coordinate_columns[0]],
+        values[coordinate_columns[1]],
+        values[coordinate_columns[2]],
+        payload,
+    );
+}
+
+fn read_binary<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    element: &str,
+) -> Result<(), KdtreeError> {
+    reader.read_exact(buffer).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => {
+            KdtreeError::InvalidFormat(format!("binary data ends inside the {} element", element))
+        }
+        _ => KdtreeError::Io(e),
+    })
+}
+
+fn parse_field(field: &str, line: usize) -> Result<f64, KdtreeError> {
+    match field.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => Err(parse_error(
+            line,
+            format!("{:?} is not a finite number", field),
+        )),
+        Err(_) => Err(parse_error(line, format!("{:?} is not a number", field))),
+    }
+}
+
+fn overflow(element: &PlyElement) -> KdtreeError {
+    KdtreeError::InvalidFormat(format!("size of the {} element overflows", element.name))
+}
+
+fn parse_error(line: usize, message: String) -> KdtreeError {
+    KdtreeError::Parse { line, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_xyz_with_payload() {
+        let input = "# scan\n1 2 3 0.5\n\n4 5 6 0.25\n";
+        let cloud = read_xyz(input.as_bytes()).unwrap();
+
+        assert_eq!(2, cloud.len());
+        assert_eq!(&[4., 5., 6.], cloud.points[1].dims());
+        assert_eq!(&["column3".to_string()], cloud.payload_names());
+        assert_eq!(&[0.25], cloud.payload(1));
+
+        let tree = cloud.build_tree().unwrap();
+        assert_eq!(
+            1,
+            tree.nearest_search(&CloudPoint::new(0, 4., 4., 4.)).index
+        );
+    }
+
+    #[test]
+    fn xyz_errors_name_the_line() {
+        match read_xyz("1 2 3\n1 2 x\n".as_bytes()) {
+            Err(KdtreeError::Parse { line, .. }) => assert_eq!(2, line),
+            _ => panic!("expected parse error"),
+        }
+        match read_xyz("1 2 3\n\n1 2 3 4\n".as_bytes()) {
+            Err(KdtreeError::Parse { line, .. }) => assert_eq!(3, line),
+            _ => panic!("expected parse error"),
+        }
+        assert!(matches!(
+            read_xyz("1 2\n".as_bytes()),
+            Err(KdtreeError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn reads_csv_with_configured_columns() {
+        let input = "intensity;z;y;x\n7;3;2;1\n8;6;5;4\n";
+        let options = CsvOptions {
+            delimiter: ';',
+            has_header: true,
+            x_column: 3,
+            y_column: 2,
+            z_column: 1,
+        };
+        let cloud = read_csv(input.as_bytes(), &options).unwrap();
+
+        assert_eq!(&[1., 2., 3.], cloud.points[0].dims());
+        assert_eq!(&["intensity".to_string()], cloud.payload_names());
+        assert_eq!(&[8.], cloud.payload(1));
+
+        let input = "1,2,3\n4,5,six\n";
+        let options = CsvOptions {
+            has_header: false,
+            ..CsvOptions::default()
+        };
+        assert!(matches!(
+            read_csv(input.as_bytes(), &options),
+            Err(KdtreeError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn reads_ascii_ply() {
+        let input = "ply\nformat ascii 1.0\ncomment made by hand\nelement vertex 2\n\
+                     property float x\nproperty float y\nproperty float z\nproperty uchar red\n\
+                     element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                     1 2 3 255\n4 5 6 128\n3 0 1 1\n";
+        let cloud = read_ply(input.as_bytes()).unwrap();
+
+        assert_eq!(2, cloud.len());
+        assert_eq!(&[4., 5., 6.], cloud.points[1].dims());
+        assert_eq!(&["red".to_string()], cloud.payload_names());
+        assert_eq!(&[255.], cloud.payload(0));
+
+        let broken = input.replace("4 5 6 128", "4 5 128");
+        assert!(matches!(
+            read_ply(broken.as_bytes()),
+            Err(KdtreeError::Parse { line: 13, .. })
+        ));
+    }
+
+    #[test]
+    fn reads_binary_little_endian_ply() {
+        let mut input = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\n\
+                          property double x\nproperty float y\nproperty float z\nproperty short intensity\n\
+                          end_header\n"
+            .to_vec();
+        for (x, y, z, intensity) in [(1f64, 2f32, 3f32, -7i16), (4., 5., 6., 300)] {
+            input.extend_from_slice(&x.to_le_bytes());
+            input.extend_from_slice(&y.to_le_bytes());
+            input.extend_from_slice(&z.to_le_bytes());
+            input.extend_from_slice(&intensity.to_le_bytes());
+        }
+
+        let cloud = read_ply(&input[..]).unwrap();
+        assert_eq!(&[1., 2., 3.], cloud.points[0].dims());
+        assert_eq!(&[4., 5., 6.], cloud.points[1].dims());
+        assert_eq!(&[-7.], cloud.payload(0));
+        assert_eq!(&[300.], cloud.payload(1));
+
+        assert!(matches!(
+            read_ply(&input[..input.len() - 1]),
+            Err(KdtreeError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_huge_counts_and_non_finite_values() {
+        let header = |format: &str, count: &str| {
+            format!(
+                "ply\nformat {} 1.0\nelement extra {}\nproperty int a\nelement big {}\n\
+                 property int b\nelement vertex 1\nproperty float x\nproperty float y\n\
+                 property float z\nend_header\n",
+                format, count, count
+            )
+        };
+        let huge = usize::MAX.to_string();
+        for format in &["ascii", "binary_little_endian"] {
+            assert!(matches!(
+                read_ply(header(format, &huge).as_bytes()),
+                Err(KdtreeError::InvalidFormat(_))
+            ));
+        }
+        let mut input = header("binary_little_endian", &(1usize << 40).to_string()).into_bytes();
+        input.extend_from_slice(&[0; 64]);
+        assert!(matches!(
+            read_ply(&input[..]),
+            Err(KdtreeError::InvalidFormat(_))
+        ));
+
+        let input = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+                     property float y\nproperty float z\nend_header\n1 nan 3\n";
+        assert!(matches!(
+            read_ply(input.as_bytes()),
+            Err(KdtreeError::Parse { line: 8, .. })
+        ));
+        assert!(matches!(
+            read_xyz("1 2 inf\n".as_bytes()),
+            Err(KdtreeError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn empty_cloud_does_not_build_a_tree() {
+        let cloud = read_xyz("# nothing here\n".as_bytes()).unwrap();
+
+        assert!(matches!(cloud.build_tree(), Err(KdtreeError::EmptyInput)));
+    }
+}
@@ -0,0 +1,195 @@
+//! x86_64 SIMD kernels behind the functions in `distance`, for 2, 3, 4 and 8 dimensions.
+//!
+//! SSE2 is part of the x86_64 baseline so the 128 bit kernels are always usable, the 256 bit AVX
+//! ones are picked at runtime when the CPU supports them. Kernels are selected once per dimension
+//! count with `f64_kernel`/`f32_kernel`, which return `None` for dimension counts without a kernel.
+
+use std::arch::x86_64::*;
+use std::convert::TryInto;
+use std::ptr;
+
+pub type Kernel<T> = fn(&[T], &[T]) -> T;
+
+/// Squared euclidean distance kernel for `dims` dimensional `f64` slices.
+/// The returned kernel panics if given slices of another length.
+pub fn f64_kernel(dims: usize) -> Option<Kernel<f64>> {
+    match dims {
+        2 => Some(f64_2),
+        3 => Some(f64_3),
+        4 if is_x86_feature_detected!("avx") => Some(f64_4_avx),
+        4 => Some(f64_4),
+        8 if is_x86_feature_detected!("avx") => Some(f64_8_avx),
+        8 => Some(f64_8),
+        _ => None,
+    }
+}
+
+/// Squared euclidean distance kernel for `dims` dimensional `f32` slices.
+/// The returned kernel panics if given slices of another length.
+pub fn f32_kernel(dims: usize) -> Option<Kernel<f32>> {
+    match dims {
+        2 => Some(f32_2),
+        3 => Some(f32_3),
+        4 => Some(f32_4),
+        8 if is_x86_feature_detected!("avx") => Some(f32_8_avx),
+        8 => Some(f32_8),
+        _ => None,
+    }
+}
+
+fn array<T, const N: usize>(v: &[T]) -> &[T; N] {
+    v.try_into()
+        .expect("kernel called with the wrong dimension count")
+}
+
+fn f64_2(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 2>(a), array::<_, 2>(b));
+    unsafe { hsum_pd(squared_difference_pd(a.as_ptr(), b.as_ptr())) }
+}
+
+fn f64_3(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 3>(a), array::<_, 3>(b));
+    let last = a[2] - b[2];
+    unsafe { hsum_pd(squared_difference_pd(a.as_ptr(), b.as_ptr())) + last * last }
+}
+
+fn f64_4(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 4>(a), array::<_, 4>(b));
+    unsafe {
+        let low = squared_difference_pd(a.as_ptr(), b.as_ptr());
+        let high = squared_difference_pd(a.as_ptr().add(2), b.as_ptr().add(2));
+        hsum_pd(_mm_add_pd(low, high))
+    }
+}
+
+fn f64_8(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 8>(a), array::<_, 8>(b));
+    unsafe {
+        let mut sum = _mm_setzero_pd();
+        for i in (0..8).step_by(2) {
+            sum = _mm_add_pd(
+                sum,
+                squared_difference_pd(a.as_ptr().add(i), b.as_ptr().add(i)),
+            );
+        }
+        hsum_pd(sum)
+    }
+}
+
+// Only handed out by `f64_kernel` after AVX support was detected.
+fn f64_4_avx(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 4>(a), array::<_, 4>(b));
+    unsafe { avx_f64_4(a, b) }
+}
+
+fn f64_8_avx(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (array::<_, 8>(a), array::<_, 8>(b));
+    unsafe { avx_f64_8(a, b) }
+}
+
+#[target_feature(enable = "avx")]
+unsafe fn avx_f64_4(a: &[f64; 4], b: &[f64; 4]) -> f64 {
+    hsum_pd256(squared_difference_pd256(a.as_ptr(), b.as_ptr()))
+}
+
+#[target_feature(enable = "avx")]
+unsafe fn avx_f64_8(a: &[f64; 8], b: &[f64; 8]) -> f64 {
+    let low = squared_difference_pd256(a.as_ptr(), b.as_ptr());
+    let high = squared_difference_pd256(a.as_ptr().add(4), b.as_ptr().add(4));
+    hsum_pd256(_mm256_add_pd(low, high))
+}
+
+// Two `f32`s in the low half of a register, the upper half zeroed. The pointer is only aligned
+// for `f32`, so the 8 bytes are read unaligned rather than through an `f64` pointer.
+#[inline]
+unsafe fn load_low_ps(p: *const f32) -> __m128 {
+    _mm_castpd_ps(_mm_set_sd(ptr::read_unaligned(p as *const f64)))
+}
+
+fn f32_2(a: &[f32], b: &[f32]) -> f32 {
+    let (a, b) = (array::<_, 2>(a), array::<_, 2>(b));
+    unsafe {
+        let a = load_low_ps(a.as_ptr());
+        let b = load_low_ps(b.as_ptr());
+        let d = _mm_sub_ps(a, b);
+        hsum_ps(_mm_mul_ps(d, d))
+    }
+}
+
+fn f32_3(a: &[f32], b: &[f32]) -> f32 {
+    let (a, b) = (array::<_, 3>(a), array::<_, 3>(b));
+    unsafe {
+        let a = _mm_movelh_ps(load_low_ps(a.as_ptr()), _mm_load_ss(a.as_ptr().add(2)));
+        let b = _mm_movelh_ps(load_low_ps(b.as_ptr()), _mm_load_ss(b.as_ptr().add(2)));
+        let d = _mm_sub_ps(a, b);
+        hsum_ps(_mm_mul_ps(d, d))
+    }
+}
+
+fn f32_4(a: &[f32], b: &[f32]) -> f32 {
+    let (a, b) = (array::<_, 4>(a), array::<_, 4>(b));
+    unsafe { hsum_ps(squared_difference_ps(a.as_ptr(), b.as_ptr())) }
+}
+
+fn f32_8(a: &[f32], b: &[f32]) -> f32 {
+    let (a, b) = (array::<_, 8>(a), array::<_, 8>(b));
+    unsafe {
+        let low = squared_difference_ps(a.as_ptr(), b.as_ptr());
+        let high = squared_difference_ps(a.as_ptr().add(4), b.as_ptr().add(4));
+        hsum_ps(_mm_add_ps(low, high))
+    }
+}
+
+fn f32_8_avx(a: &[f32], b: &[f32]) -> f32 {
+    let (a, b) = (array::<_, 8>(a), array::<_, 8>(b));
+    unsafe { avx_f32_8(a, b) }
+}
+
+#[target_feature(enable = "avx")]
+unsafe fn avx_f32_8(a: &[f32; 8], b: &[f32; 8]) -> f32 {
+    let d = _mm256_sub_ps(_mm256_loadu_ps(a.as_ptr()), _mm256_loadu_ps(b.as_ptr()));
+    let squared = _mm256_mul_ps(d, d);
+    hsum_ps(_mm_add_ps(
+        _mm256_castps256_ps128(squared),
+        _mm256_extractf128_ps(squared, 1),
+    ))
+}
+
+#[inline(always)]
+unsafe fn squared_difference_pd(a: *const f64, b: *const f64) -> __m128d {
+    let d = _mm_sub_pd(_mm_loadu_pd(a), _mm_loadu_pd(b));
+    _mm_mul_pd(d, d)
+}
+
+#[inline(always)]
+unsafe fn squared_difference_ps(a: *const f32, b: *const f32) -> __m128 {
+    let d = _mm_sub_ps(_mm_loadu_ps(a), _mm_loadu_ps(b));
+    _mm_mul_ps(d, d)
+}
+
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn squared_difference_pd256(a: *const f64, b: *const f64) -> __m256d {
+    let d = _mm256_sub_pd(_mm256_loadu_pd(a), _mm256_loadu_pd(b));
+    _mm256_mul_pd(d, d)
+}
+
+#[inline(always)]
+unsafe fn hsum_pd(v: __m128d) -> f64 {
+    _mm_cvtsd_f64(_mm_add_sd(v, _mm_unpackhi_pd(v, v)))
+}
+
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn hsum_pd256(v: __m256d) -> f64 {
+    hsum_pd(_mm_add_pd(
+        _mm256_castpd256_pd128(v),
+        _mm256_extractf128_pd(v, 1),
+    ))
+}
+
+#[inline(always)]
+unsafe fn hsum_ps(v: __m128) -> f32 {
+    let pairs = _mm_add_ps(v, _mm_movehl_ps(v, v));
+    _mm_cvtss_f32(_mm_add_ss(pairs, _mm_shuffle_ps(pairs, pairs, 1)))
+}
@@ -0,0 +1,173 @@
+//! Rules deciding on which dimension and at which point `build_tree` splits a set of points.
+
+use std::cmp::Ordering;
+
+use bounds::Bounds;
+use kdtree::KdtreePointTrait;
+use partition;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How a tree is split while building, picked with `KdtreeBuilder::split_rule`.
+///
+/// Whatever the rule, the point a node is split on is stored in that node, points equal to it
+/// may end up on either side.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SplitRule {
+    /// Splits the widest dimension of the node's cell in the middle, sliding the split to the
+    /// closest point when all points are on one side. Fast to build, the default.
+    #[default]
+    SlidingMidpoint,
+    /// Splits the widest dimension of the points' bounding box at the point closest to its middle.
+    Midpoint,
+    /// Splits the widest dimension of the points' bounding box at the median point,
+    /// giving a balanced tree of minimal depth at a higher build cost.
+    Median,
+    /// Splits the dimension along which the points vary most at the median point,
+    /// which suits clustered data whose widest extent is driven by a few outliers.
+    MaxVariance,
+}
+
+impl SplitRule {
+    /// Reorders `points` around the splitting point and returns the splitting dimension together
+    /// with the index of that point. Points before the index are `<=` it on that dimension,
+    /// points after it are `>=` it.
+    pub(crate) fn split<T: KdtreePointTrait>(
+        self,
+        points: &mut [T],
+        cell: &Bounds,
+    ) -> (usize, usize) {
+        match self {
+            SplitRule::SlidingMidpoint => {
+                let dimension = cell.get_widest_dim();
+                let index = partition::partition_sliding_midpoint(
+                    points,
+                    cell.get_midvalue_of_widest_dim(),
+                    dimension,
+                );
+                (dimension, index)
+            }
+            SplitRule::Midpoint => {
+                let bounds = Bounds::new_from_points(points);
+                let dimension = bounds.get_widest_dim();
+                let index = partition::partition_sliding_midpoint(
+                    points,
+                    bounds.get_midvalue_of_widest_dim(),
+                    dimension,
+                );
+                (dimension, index)
+            }
+            SplitRule::Median => {
+                let dimension = Bounds::new_from_points(points).get_widest_dim();
+                (dimension, partition_at_median(points, dimension))
+            }
+            SplitRule::MaxVariance => {
+                let dimension = dimension_of_max_variance(points);
+                (dimension, partition_at_median(points, dimension))
+            }
+        }
+    }
+}
+
+fn partition_at_median<T: KdtreePointTrait>(points: &mut [T], dimension: usize) -> usize {
+    let median = points.len() / 2;
+    points.select_nth_unstable_by(median, |a, b| {
+        a.dims()[dimension]
+            .partial_cmp(&b.dims()[dimension])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    median
+}
+
+fn dimension_of_max_variance<T: KdtreePointTrait>(points: &[T]) -> usize {
+    let dimensions = points[0].dims().len();
+    let count = points.len() as f64;
+
+    let mut mean = vec![0.; dimensions];
+    for p in points {
+        for (m, value) in mean.iter_mut().zip(p.dims()) {
+            *m += value / count;
+        }
+    }
+
+    let mut variance = vec![0.; dimensions];
+    for p in points {
+        for ((v, m), value) in variance.iter_mut().zip(&mean).zip(p.dims()) {
+            *v += (value - m) * (value - m);
+        }
+    }
+
+    let mut widest = 0;
+    for (dimension, v) in variance.iter().enumerate() {
+        if *v > variance[widest] {
+            widest = dimension;
+        }
+    }
+
+    widest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::Point2WithId;
+
+    fn points() -> Vec<Point2WithId> {
+        // wide along x because of one outlier, but most of the spread is along y.
+        let mut points: Vec<_> = (0..9)
+            .map(|i| Point2WithId::new(i, f64::from(i % 2), f64::from(2 * i)))
+            .collect();
+        points.push(Point2WithId::new(9, 100., 8.));
+        points
+    }
+
+    fn assert_split(points: &[Point2WithId], dimension: usize, index: usize) {
+        let pivot = points[index].dims()[dimension];
+        assert!(points[..index].iter().all(|p| p.dims()[dimension] <= pivot));
+        assert!(points[index + 1..]
+            .iter()
+            .all(|p| p.dims()[dimension] >= pivot));
+    }
+
+    #[test]
+    fn median_splits_widest_dimension_in_half() {
+        let mut points = points();
+        let bounds = Bounds::new_from_points(&points);
+
+        let (dimension, index) = SplitRule::Median.split(&mut points, &bounds);
+        assert_eq!((0, 5), (dimension, index));
+        assert_split(&points, dimension, index);
+    }
+
+    #[test]
+    fn max_variance_ignores_a_single_outlier() {
+        let mut points = points();
+        points[9] = Point2WithId::new(9, 16.5, 8.);
+        let bounds = Bounds::new_from_points(&points);
+        assert_eq!(0, bounds.get_widest_dim());
+
+        let (dimension, index) = SplitRule::MaxVariance.split(&mut points, &bounds);
+        assert_eq!((1, 5), (dimension, index));
+        assert_split(&points, dimension, index);
+    }
+
+    #[test]
+    fn midpoint_uses_the_points_bounding_box() {
+        let mut points = points();
+        // a cell much wider than the points along y.
+        let cell = Bounds::new_from_points(&[
+            Point2WithId::new(0, 0., -1000.),
+            Point2WithId::new(0, 0., 1000.),
+        ]);
+
+        let (dimension, index) = SplitRule::Midpoint.split(&mut points, &cell);
+        assert_eq!(0, dimension);
+        assert_split(&points, dimension, index);
+
+        let (dimension, _) = SplitRule::SlidingMidpoint.split(&mut points, &cell);
+        assert_eq!(1, dimension);
+    }
+}
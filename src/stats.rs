@@ -0,0 +1,168 @@
+//! Shape of a tree and cost of queries on it, to help tuning inserts, rebuilds and query patterns.
+
+use std::mem;
+
+use kdtree::{Kdtree, KdtreeNode, KdtreePointTrait};
+
+/// Snapshot of a tree's shape returned by `Kdtree::stats`. Depths count the root as depth 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    pub node_count: usize,
+    /// Depth of the deepest leaf.
+    pub max_leaf_depth: usize,
+    pub mean_leaf_depth: f64,
+    /// Depth of the tree right after it was last built, inserts rebuild it once they go
+    /// `TreeSettings::rebuild_depth_factor` times deeper.
+    pub node_depth_during_last_rebuild: usize,
+    pub inserts_since_last_rebuild: usize,
+    /// How many nodes split on each dimension, indexed by dimension.
+    pub splits_per_dimension: Vec<usize>,
+    /// Bytes used by the tree itself, heap memory owned by the points is not included.
+    pub memory_bytes: usize,
+}
+
+impl TreeStats {
+    /// Depth of the deepest leaf compared to the depth of a perfectly balanced tree
+    /// with the same node count, 1.0 means perfectly balanced.
+    pub fn imbalance(&self) -> f64 {
+        let balanced_depth = (self.node_count as f64 + 1.).log2().ceil();
+
+        self.max_leaf_depth as f64 / balanced_depth
+    }
+}
+
+/// Work done by a single query, returned by the `*_with_stats` query variants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    pub nodes_visited: usize,
+    /// Distances computed between the query and a stored point, splitting plane checks are not counted.
+    pub distance_evaluations: usize,
+    /// Subtrees skipped because they could not contain a better result.
+    pub subtrees_pruned: usize,
+}
+
+// What the searches report to. `()` counts nothing, so the plain queries compile to the same
+// code as before the counters existed, while the `*_with_stats` variants pass a `QueryStats`.
+pub(crate) trait QueryCounter {
+    fn node_visited(&mut self);
+    fn distance_evaluated(&mut self);
+    fn subtree_pruned(&mut self);
+}
+
+impl QueryCounter for () {
+    #[inline]
+    fn node_visited(&mut self) {}
+    #[inline]
+    fn distance_evaluated(&mut self) {}
+    #[inline]
+    fn subtree_pruned(&mut self) {}
+}
+
+impl QueryCounter for QueryStats {
+    #[inline]
+    fn node_visited(&mut self) {
+        self.nodes_visited += 1;
+    }
+    #[inline]
+    fn distance_evaluated(&mut self) {
+        self.distance_evaluations += 1;
+    }
+    #[inline]
+    fn subtree_pruned(&mut self) {
+        self.subtrees_pruned += 1;
+    }
+}
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    pub fn stats(&self) -> TreeStats {
+        let dimensions = self.nodes[0].point.dims().len();
+        let mut splits_per_dimension = vec![0; dimensions];
+        let mut leaf_count = 0usize;
+        let mut leaf_depth_sum = 0usize;
+        let mut max_leaf_depth = 0usize;
+
+        let mut to_visit = vec![(0usize, 1usize)];
+        while let Some((index, depth)) = to_visit.pop() {
+            let node = &self.nodes[index];
+            splits_per_dimension[node.dimension] += 1;
+
+            if node.left_node.is_none() && node.right_node.is_none() {
+                leaf_count += 1;
+                leaf_depth_sum += depth;
+                max_leaf_depth = max_leaf_depth.max(depth);
+            }
+
+            for child in node.left_node.iter().chain(node.right_node.iter()) {
+                to_visit.push((*child, depth + 1));
+            }
+        }
+
+        TreeStats {
+            node_count: self.nodes.len(),
+            max_leaf_depth,
+            mean_leaf_depth: leaf_depth_sum as f64 / leaf_count as f64,
+            node_depth_during_last_rebuild: self.node_depth_during_last_rebuild,
+            inserts_since_last_rebuild: self.inserts_since_last_rebuild,
+            splits_per_dimension,
+            memory_bytes: mem::size_of::<Self>()
+                + self.nodes.capacity() * mem::size_of::<KdtreeNode<KdtreePoint>>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::squared_euclidean;
+    use kdtree::Kdtree;
+    use test_common::Point2WithId;
+
+    #[test]
+    fn counts_nodes_depths_and_inserts() {
+        let mut points = vec![
+            Point2WithId::new(0, 0., 0.),
+            Point2WithId::new(1, 1., 0.),
+            Point2WithId::new(2, 2., 0.),
+        ];
+        let mut tree = Kdtree::new(&mut points).unwrap();
+
+        let stats = tree.stats();
+        assert_eq!(3, stats.node_count);
+        assert_eq!(2, stats.max_leaf_depth);
+        assert_eq!(2., stats.mean_leaf_depth);
+        assert_eq!(2, stats.node_depth_during_last_rebuild);
+        assert_eq!(0, stats.inserts_since_last_rebuild);
+        assert_eq!(vec![3, 0], stats.splits_per_dimension);
+        assert_eq!(1., stats.imbalance());
+        assert!(stats.memory_bytes > 0);
+
+        tree.insert_node(Point2WithId::new(3, 3., 1.));
+        tree.insert_node(Point2WithId::new(3, 3., 1.));
+        let stats = tree.stats();
+        assert_eq!(4, stats.node_count);
+        assert_eq!(1, stats.inserts_since_last_rebuild);
+        assert_eq!(3, stats.max_leaf_depth);
+
+        tree.gather_points_and_rebuild();
+        assert_eq!(0, tree.stats().inserts_since_last_rebuild);
+    }
+
+    #[test]
+    fn query_stats_count_visits_and_pruning() {
+        let mut points: Vec<_> = (0..100)
+            .map(|i| Point2WithId::new(i, f64::from(i % 10), f64::from(i / 10)))
+            .collect();
+        let tree = Kdtree::new(&mut points).unwrap();
+        let query = Point2WithId::new(-1, 0.1, 0.1);
+
+        let (found, stats) = tree.nearest_search_with_stats(&query);
+        assert_eq!(tree.nearest_search(&query), found);
+        assert!(stats.nodes_visited > 0 && stats.nodes_visited < 100);
+        assert_eq!(stats.nodes_visited + 1, stats.distance_evaluations);
+        assert!(stats.subtrees_pruned > 0);
+
+        let (found, stats) = tree.within_with_stats(&query, 1.5, &squared_euclidean);
+        assert_eq!(tree.within(&query, 1.5, &squared_euclidean), found);
+        assert!(stats.nodes_visited < 100);
+        assert!(stats.subtrees_pruned > 0);
+    }
+}
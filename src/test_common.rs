@@ -1,6 +1,10 @@
 use kdtree::KdtreePointTrait;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point3WithId {
     dims: [f64; 3],
     pub id: i32,
@@ -23,6 +27,7 @@ impl KdtreePointTrait for Point3WithId {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point2WithId {
     dims: [f64; 2],
     pub id: i32,
@@ -45,6 +50,7 @@ impl KdtreePointTrait for Point2WithId {
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point1WithId {
     dims: [f64; 1],
     pub id: i32,
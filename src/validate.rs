@@ -0,0 +1,187 @@
+//! Structural checks of a tree, for debugging long sequences of inserts and rebuilds.
+
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+// Closest ancestor split bounding a dimension, with the index of the ancestor that set it.
+type Limit = Option<(f64, usize)>;
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Walks the whole tree and returns the first broken invariant as `KdtreeError::InvalidTree`.
+    ///
+    /// Checked are that every node is reachable from the root exactly once (so there are no cycles
+    /// or shared children), that child indices and splitting dimensions are in range, that all points
+    /// have the same dimension count, and that points in a left subtree are `<= split_on` and points
+    /// in a right subtree are `>= split_on` of each ancestor. Points equal to the split may end up on
+    /// either side when building, so equality is allowed on the right too.
+    pub fn validate(&self) -> Result<(), KdtreeError> {
+        if self.nodes.is_empty() {
+            return Err(invalid(0, "tree has no nodes".to_string()));
+        }
+
+        let dimensions = self.nodes[0].point.dims().len();
+        let mut reached = vec![false; self.nodes.len()];
+        let mut to_visit: Vec<(usize, Vec<(Limit, Limit)>)> =
+            vec![(0, vec![(None, None); dimensions])];
+
+        while let Some((index, limits)) = to_visit.pop() {
+            if reached[index] {
+                return Err(invalid(
+                    index,
+                    "node is reached more than once, the tree has a cycle or a shared child"
+                        .to_string(),
+                ));
+            }
+            reached[index] = true;
+
+            let node = &self.nodes[index];
+            let dims = node.point.dims();
+            if dims.len() != dimensions {
+                return Err(invalid(
+                    index,
+                    format!(
+                        "point has {} dimensions but the root has {}",
+                        dims.len(),
+                        dimensions
+                    ),
+                ));
+            }
+            if node.dimension >= dimensions {
+                return Err(invalid(
+                    index,
+                    format!(
+                        "splits on dimension {} of a {} dimensional tree",
+                        node.dimension, dimensions
+                    ),
+                ));
+            }
+
+            for (dimension, (lower, upper)) in limits.iter().enumerate() {
+                let value = dims[dimension];
+                if let Some((split_on, ancestor)) = *lower {
+                    if value < split_on {
+                        return Err(invalid(
+                            index,
+                            format!(
+                                "coordinate {} on dimension {} is below {}, the split of node {} it is right of",
+                                value, dimension, split_on, ancestor
+                            ),
+                        ));
+                    }
+                }
+                if let Some((split_on, ancestor)) = *upper {
+                    if value > split_on {
+                        return Err(invalid(
+                            index,
+                            format!(
+                                "coordinate {} on dimension {} is above {}, the split of node {} it is left of",
+                                value, dimension, split_on, ancestor
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            for &(child, is_left) in [(node.right_node, false), (node.left_node, true)].iter() {
+                let child = match child {
+                    Some(child) => child,
+                    None => continue,
+                };
+                if child >= self.nodes.len() {
+                    return Err(invalid(
+                        index,
+                        format!(
+                            "child index {} is out of range of {} nodes",
+                            child,
+                            self.nodes.len()
+                        ),
+                    ));
+                }
+
+                let mut child_limits = limits.clone();
+                if is_left {
+                    child_limits[node.dimension].1 = Some((node.split_on, index));
+                } else {
+                    child_limits[node.dimension].0 = Some((node.split_on, index));
+                }
+                to_visit.push((child, child_limits));
+            }
+        }
+
+        match reached.iter().position(|r| !r) {
+            Some(index) => Err(invalid(
+                index,
+                "node is not reachable from the root".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid(node: usize, message: String) -> KdtreeError {
+    KdtreeError::InvalidTree { node, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use error::KdtreeError;
+    use kdtree::Kdtree;
+    use test_common::Point2WithId;
+
+    fn grid_tree() -> Kdtree<Point2WithId> {
+        let mut points: Vec<_> = (0..25)
+            .map(|i| Point2WithId::new(i, f64::from(i % 5), f64::from(i / 5)))
+            .collect();
+        Kdtree::new(&mut points).unwrap()
+    }
+
+    fn violating_node(tree: &Kdtree<Point2WithId>) -> usize {
+        match tree.validate() {
+            Err(KdtreeError::InvalidTree { node, .. }) => node,
+            other => panic!("expected an invalid tree, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn built_and_inserted_trees_are_valid() {
+        let mut tree = grid_tree();
+        assert!(tree.validate().is_ok());
+
+        for i in 0..50 {
+            tree.insert_node(Point2WithId::new(100 + i, f64::from(i % 7), 2.));
+            assert!(tree.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn reports_points_on_the_wrong_side_of_a_split() {
+        let mut tree = grid_tree();
+        let left = tree.nodes[0].left_node.unwrap();
+        let dimension = tree.nodes[0].dimension;
+        tree.nodes[left].point = if dimension == 0 {
+            Point2WithId::new(-1, 10., 0.)
+        } else {
+            Point2WithId::new(-1, 0., 10.)
+        };
+
+        assert_eq!(left, violating_node(&tree));
+    }
+
+    #[test]
+    fn reports_cycles_and_unreachable_nodes() {
+        let mut tree = grid_tree();
+        let leaf = (0..tree.nodes.len())
+            .find(|&i| tree.nodes[i].left_node.is_none() && tree.nodes[i].right_node.is_none())
+            .unwrap();
+        tree.nodes[leaf].left_node = Some(0);
+        assert_eq!(0, violating_node(&tree));
+
+        let mut tree = grid_tree();
+        let right = tree.nodes[0].right_node.take().unwrap();
+        assert_eq!(right, violating_node(&tree));
+
+        let mut tree = grid_tree();
+        tree.nodes[0].left_node = Some(1000);
+        assert_eq!(0, violating_node(&tree));
+    }
+}
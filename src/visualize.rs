@@ -0,0 +1,174 @@
+//! GraphViz DOT and SVG pictures of a tree, behind the `visualize` feature.
+//!
+//! `to_dot` works for any dimension, render it with e.g. `dot -Tpng tree.dot -o tree.png`.
+//! `to_svg` draws 2 dimensional trees: every point and, clipped to its cell, the line its node splits on.
+
+use std::fmt::Write;
+
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+const SVG_SIZE: f64 = 512.;
+const SVG_MARGIN: f64 = 16.;
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// One graph node per tree node labelled with its index, splitting dimension,
+    /// `split_on` value and point, edges are labelled with the side of the split.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph kdtree {\n    node [shape=box, fontname=monospace];\n");
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                dot,
+                "    n{} [label=\"#{}\\nd{} split {}\\n{}\"];",
+                index,
+                index,
+                node.dimension,
+                node.split_on,
+                format_point(node.point.dims())
+            )
+            .unwrap();
+        }
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Some(left) = node.left_node {
+                writeln!(dot, "    n{} -> n{} [label=\"<=\"];", index, left).unwrap();
+            }
+            if let Some(right) = node.right_node {
+                writeln!(dot, "    n{} -> n{} [label=\">=\"];", index, right).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Draws the points and split lines of a 2 dimensional tree, y pointing up.
+    pub fn to_svg(&self) -> Result<String, KdtreeError> {
+        let dimensions = self.nodes[0].point.dims().len();
+        if dimensions != 2 {
+            return Err(KdtreeError::DimensionMismatch {
+                expected: 2,
+                actual: dimensions,
+            });
+        }
+
+        let cells: Vec<_> = self.iter_cells().collect();
+        let (min, max) = (&cells[0].min, &cells[0].max);
+        let extent = (max[0] - min[0]).max(max[1] - min[1]);
+        let scale = if extent > 0. { SVG_SIZE / extent } else { 1. };
+        let to_svg = |x: f64, y: f64| {
+            (
+                SVG_MARGIN + (x - min[0]) * scale,
+                SVG_MARGIN + (max[1] - y) * scale,
+            )
+        };
+
+        let side = SVG_SIZE + 2. * SVG_MARGIN;
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">",
+            side
+        )
+        .unwrap();
+        let (x0, y0) = to_svg(min[0], max[1]);
+        let (x1, y1) = to_svg(max[0], min[1]);
+        writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\"/>",
+            x0,
+            y0,
+            x1 - x0,
+            y1 - y0
+        )
+        .unwrap();
+
+        for cell in &cells {
+            let ((x0, y0), (x1, y1)) = if cell.dimension == 0 {
+                (
+                    to_svg(cell.split_on, cell.min[1]),
+                    to_svg(cell.split_on, cell.max[1]),
+                )
+            } else {
+                (
+                    to_svg(cell.min[0], cell.split_on),
+                    to_svg(cell.max[0], cell.split_on),
+                )
+            };
+            writeln!(
+                svg,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"/>",
+                x0,
+                y0,
+                x1,
+                y1,
+                if cell.dimension == 0 { "red" } else { "blue" }
+            )
+            .unwrap();
+        }
+
+        for cell in &cells {
+            let dims = cell.point.dims();
+            let (x, y) = to_svg(dims[0], dims[1]);
+            writeln!(svg, "  <circle cx=\"{}\" cy=\"{}\" r=\"3\"/>", x, y).unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+}
+
+fn format_point(dims: &[f64]) -> String {
+    let coords: Vec<String> = dims.iter().map(|d| d.to_string()).collect();
+    format!("({})", coords.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use error::KdtreeError;
+    use kdtree::Kdtree;
+    use test_common::{Point2WithId, Point3WithId};
+
+    fn tree() -> Kdtree<Point2WithId> {
+        let mut points = vec![
+            Point2WithId::new(0, 0., 0.),
+            Point2WithId::new(1, 4., 1.),
+            Point2WithId::new(2, 2., 2.),
+        ];
+        Kdtree::new(&mut points).unwrap()
+    }
+
+    #[test]
+    fn dot_has_a_node_per_tree_node_and_labelled_edges() {
+        let dot = tree().to_dot();
+
+        assert!(dot.starts_with("digraph kdtree {"));
+        assert_eq!(3, dot.matches("[label=\"#").count());
+        assert_eq!(1, dot.matches("[label=\"<=\"]").count());
+        assert_eq!(1, dot.matches("[label=\">=\"]").count());
+        assert!(dot.contains("#0\\nd0 split 2\\n(2, 2)"));
+    }
+
+    #[test]
+    fn svg_draws_points_and_split_lines() {
+        let svg = tree().to_svg().unwrap();
+
+        assert_eq!(3, svg.matches("<circle").count());
+        assert_eq!(3, svg.matches("<line").count());
+        // the root splits x at 2, halfway across the 4 wide bounding box.
+        assert!(svg.contains("<line x1=\"272\" y1=\"272\" x2=\"272\" y2=\"16\" stroke=\"red\"/>"));
+    }
+
+    #[test]
+    fn svg_needs_two_dimensions() {
+        let tree = Kdtree::new(&mut [Point3WithId::new(0, 0., 0., 0.)]).unwrap();
+
+        match tree.to_svg() {
+            Err(KdtreeError::DimensionMismatch { expected, actual }) => {
+                assert_eq!((2, 3), (expected, actual))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
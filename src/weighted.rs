@@ -0,0 +1,256 @@
+//! Queries under a weighted euclidean metric, for coordinates with very different scales.
+//!
+//! `weights` holds one non negative weight per dimension, the squared distance is
+//! `sum(weights[i] * (a[i] - b[i])^2)`. The tree itself is unchanged, pruning scales the
+//! distance to each split plane by the weight of its dimension.
+//!
+//! Queries fail with `DimensionMismatch` unless the query and `weights` have the tree's dimension
+//! count, and with `InvalidMetric` if a weight is negative or not finite, which would break pruning.
+
+use distance::weighted_squared_euclidean;
+use error::KdtreeError;
+use kdtree::{Kdtree, KdtreePointTrait};
+
+impl<KdtreePoint: KdtreePointTrait> Kdtree<KdtreePoint> {
+    /// Nearest point to `node` under the weighted euclidean metric.
+    pub fn nearest_search_weighted(
+        &self,
+        node: &KdtreePoint,
+        weights: &[f64],
+    ) -> Result<KdtreePoint, KdtreeError> {
+        self.check_weights(node.dims(), weights)?;
+
+        let mut nearest_neighbor = 0usize;
+        let mut best_distance =
+            weighted_squared_euclidean(node.dims(), self.nodes[0].point.dims(), weights);
+        self.nearest_search_weighted_impl(
+            node.dims(),
+            weights,
+            0usize,
+            &mut best_distance,
+            &mut nearest_neighbor,
+        );
+
+        Ok(self.nodes[nearest_neighbor].point.clone())
+    }
+
+    /// Points whose weighted euclidean distance to `node` is at most `radius`,
+    /// none for a negative or NaN radius.
+    pub fn within_weighted(
+        &self,
+        node: &KdtreePoint,
+        radius: f64,
+        weights: &[f64],
+    ) -> Result<Vec<&KdtreePoint>, KdtreeError> {
+        self.check_weights(node.dims(), weights)?;
+        // a negative radius would square to a positive one.
+        if radius.is_nan() || radius < 0. {
+            return Ok(vec![]);
+        }
+
+        let mut found = vec![];
+        self.within_weighted_impl(node.dims(), weights, radius * radius, 0usize, &mut found);
+
+        Ok(found.into_iter().map(|x| &self.nodes[x].point).collect())
+    }
+
+    fn check_weights(&self, query: &[f64], weights: &[f64]) -> Result<(), KdtreeError> {
+        let dimensions = self.nodes[0].point.dims().len();
+        for actual in &[query.len(), weights.len()] {
+            if *actual != dimensions {
+                return Err(KdtreeError::DimensionMismatch {
+                    expected: dimensions,
+                    actual: *actual,
+                });
+            }
+        }
+
+        match weights.iter().find(|w| !(w.is_finite() && **w >= 0.)) {
+            Some(weight) => Err(KdtreeError::InvalidMetric(format!(
+                "weight {} is not a finite non negative number",
+                weight
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn nearest_search_weighted_impl(
+        &self,
+        p: &[f64],
+        weights: &[f64],
+        searched_index: usize,
+        best_distance_squared: &mut f64,
+        best_leaf_found: &mut usize,
+    ) {
+        let node = &self.nodes[searched_index];
+
+        let splitting_value = node.split_on;
+        let point_splitting_dim_value = p[node.dimension];
+
+        let (closer_node, farther_node) = if point_splitting_dim_value <= splitting_value {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.nearest_search_weighted_impl(
+                p,
+                weights,
+                closer_node,
+                best_distance_squared,
+                best_leaf_found,
+            );
+        }
+
+        let distance = weighted_squared_euclidean(p, node.point.dims(), weights);
+        if distance < *best_distance_squared {
+            *best_distance_squared = distance;
+            *best_leaf_found = searched_index;
+        }
+
+        if let Some(farther_node) = farther_node {
+            let to_plane = point_splitting_dim_value - splitting_value;
+
+            if weights[node.dimension] * to_plane * to_plane <= *best_distance_squared {
+                self.nearest_search_weighted_impl(
+                    p,
+                    weights,
+                    farther_node,
+                    best_distance_squared,
+                    best_leaf_found,
+                );
+            }
+        }
+    }
+
+    fn within_weighted_impl(
+        &self,
+        p: &[f64],
+        weights: &[f64],
+        squared_radius: f64,
+        searched_index: usize,
+        found: &mut Vec<usize>,
+    ) {
+        let node = &self.nodes[searched_index];
+
+        if weighted_squared_euclidean(p, node.point.dims(), weights) <= squared_radius {
+            found.push(searched_index);
+        }
+
+        let to_plane = p[node.dimension] - node.split_on;
+        let plane_distance = weights[node.dimension] * to_plane * to_plane;
+        let (closer_node, farther_node) = if to_plane <= 0. {
+            (node.left_node, node.right_node)
+        } else {
+            (node.right_node, node.left_node)
+        };
+
+        if let Some(closer_node) = closer_node {
+            self.within_weighted_impl(p, weights, squared_radius, closer_node, found);
+        }
+        if let Some(farther_node) = farther_node {
+            if plane_distance <= squared_radius {
+                self.within_weighted_impl(p, weights, squared_radius, farther_node, found);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use distance::weighted_squared_euclidean;
+    use error::KdtreeError;
+    use kdtree::{Kdtree, KdtreePointTrait};
+    use test_common::Point2WithId;
+
+    #[test]
+    fn same_results_as_linear_search() {
+        let points: Vec<_> = (0..200)
+            .map(|i| {
+                let x = f64::from((i * 37) % 200);
+                let y = f64::from((i * 71) % 200) / 100.;
+                Point2WithId::new(i, x, y)
+            })
+            .collect();
+        let tree = Kdtree::from_vec(points.clone()).unwrap();
+        let weights = [0.0001, 1.];
+
+        for q in (0..40).map(|i| Point2WithId::new(-1, f64::from(i) * 5.1, f64::from(i % 9) * 0.23))
+        {
+            let distance =
+                |p: &Point2WithId| weighted_squared_euclidean(q.dims(), p.dims(), &weights);
+
+            let nearest = tree.nearest_search_weighted(&q, &weights).unwrap();
+            let linear = points.iter().map(&distance).fold(f64::INFINITY, f64::min);
+            assert_eq!(linear, distance(&nearest));
+
+            let mut found: Vec<i32> = tree
+                .within_weighted(&q, 0.3, &weights)
+                .unwrap()
+                .iter()
+                .map(|p| p.id)
+                .collect();
+            found.sort_unstable();
+            let linear: Vec<i32> = points
+                .iter()
+                .filter(|p| distance(p) <= 0.3 * 0.3)
+                .map(|p| p.id)
+                .collect();
+            assert_eq!(linear, found);
+        }
+    }
+
+    #[test]
+    fn weights_change_the_nearest_point() {
+        let tree = Kdtree::from_vec(vec![
+            Point2WithId::new(0, 3., 0.),
+            Point2WithId::new(1, 0., 1.),
+        ])
+        .unwrap();
+        let query = Point2WithId::new(-1, 0., 0.);
+
+        assert_eq!(
+            1,
+            tree.nearest_search_weighted(&query, &[1., 1.]).unwrap().id
+        );
+        assert_eq!(
+            0,
+            tree.nearest_search_weighted(&query, &[0.01, 1.])
+                .unwrap()
+                .id
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_weights() {
+        let tree = Kdtree::from_vec(vec![
+            Point2WithId::new(0, 3., 0.),
+            Point2WithId::new(1, 0., 1.),
+        ])
+        .unwrap();
+        let query = Point2WithId::new(-1, 0., 0.);
+
+        for weights in &[[-1., 1.], [f64::NAN, 1.], [1., f64::INFINITY]] {
+            assert!(matches!(
+                tree.nearest_search_weighted(&query, weights),
+                Err(KdtreeError::InvalidMetric(_))
+            ));
+            assert!(matches!(
+                tree.within_weighted(&query, 5., weights),
+                Err(KdtreeError::InvalidMetric(_))
+            ));
+        }
+        assert!(matches!(
+            tree.nearest_search_weighted(&query, &[1.]),
+            Err(KdtreeError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(tree
+            .within_weighted(&query, -5., &[1., 1.])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "cli")]
+
+extern crate serde_json;
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use serde_json::Value;
+
+// Temporary file removed again when the guard is dropped, also when a test fails.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> TempFile {
+        TempFile(std::env::temp_dir().join(format!("kdtree-cli-{}-{}", std::process::id(), name)))
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn kdtree(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kdtree"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn ids(line: &str) -> Vec<u64> {
+    let value: Value = serde_json::from_str(line).unwrap();
+    value["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_u64().unwrap())
+        .collect()
+}
+
+#[test]
+fn builds_saves_and_queries_a_tree() {
+    let points = TempFile::new("points.csv");
+    let tree_file = TempFile::new("tree.kdt");
+    fs::write(
+        &points.0,
+        "x,y,z,intensity\n0,0,0,1\n1,0,0,2\n0,5,0,3\n3,3,3,4\n",
+    )
+    .unwrap();
+
+    let built = kdtree(&["build", points.path(), "-o", tree_file.path()], "");
+    assert!(built.status.success(), "{:?}", built);
+
+    let tree = tree_file.path();
+    let nearest = kdtree(&["query", tree, "nearest", "0.9,0.1,0"], "");
+    assert!(nearest.status.success());
+    assert_eq!(vec![1], ids(&String::from_utf8(nearest.stdout).unwrap()));
+
+    let knn = kdtree(&["query", tree, "knn", "2", "0,0,0"], "");
+    assert_eq!(vec![0, 1], ids(&String::from_utf8(knn.stdout).unwrap()));
+    let knn = kdtree(
+        &["query", tree, "knn", &usize::MAX.to_string(), "0,0,0"],
+        "",
+    );
+    assert!(knn.status.success(), "{:?}", knn);
+    assert_eq!(4, ids(&String::from_utf8(knn.stdout).unwrap()).len());
+
+    let boxed = kdtree(&["query", tree, "box", "-1,-1,-1", "1,5,1"], "");
+    let mut found = ids(&String::from_utf8(boxed.stdout).unwrap());
+    found.sort_unstable();
+    assert_eq!(vec![0, 1, 2], found);
+
+    let streamed = kdtree(
+        &["query", tree, "--stdin"],
+        "{\"op\": \"radius\", \"r\": 1.5, \"point\": [0, 0, 0]}\n\n{\"op\": \"nearest\", \"point\": [3, 3, 2]}\n",
+    );
+    let stdout = String::from_utf8(streamed.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(2, lines.len());
+    assert_eq!(vec![0, 1], ids(lines[0]));
+    assert_eq!(vec![3], ids(lines[1]));
+    assert!(lines[1].contains("\"query\":1"), "{}", lines[1]);
+
+    let csv = kdtree(
+        &[
+            "query",
+            points.path(),
+            "--format",
+            "csv",
+            "nearest",
+            "0,4,0",
+        ],
+        "",
+    );
+    assert_eq!(
+        "query,id,distance,d0,d1,d2\n0,2,1,0,5,0\n",
+        String::from_utf8(csv.stdout).unwrap()
+    );
+}
+
+#[test]
+fn reports_bad_input_without_panicking() {
+    let points = TempFile::new("broken.xyz");
+    fs::write(&points.0, "0 0 0\n1 x 0\n").unwrap();
+
+    let output = kdtree(&["query", points.path(), "nearest", "0,0,0"], "");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("line 2"), "{}", stderr);
+
+    let points = TempFile::new("ok.xyz");
+    fs::write(&points.0, "0 0 0\n1 1 0\n").unwrap();
+    let output = kdtree(&["query", points.path(), "nearest", "0,0"], "");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn reads_csv_without_a_header_and_with_other_columns() {
+    let points = TempFile::new("no-header.csv");
+    fs::write(&points.0, "7,0,0,0\n8,1,0,0\n9,0,5,0\n").unwrap();
+
+    let all = kdtree(
+        &[
+            "query",
+            points.path(),
+            "--no-header",
+            "--columns",
+            "1,2,3",
+            "knn",
+            "5",
+            "0,0,0",
+        ],
+        "",
+    );
+    assert!(all.status.success(), "{:?}", all);
+    assert_eq!(vec![0, 1, 2], ids(&String::from_utf8(all.stdout).unwrap()));
+
+    // with the default header the first point is taken as column names.
+    let headed = kdtree(&["query", points.path(), "knn", "5", "0,0,0"], "");
+    assert_eq!(2, ids(&String::from_utf8(headed.stdout).unwrap()).len());
+}
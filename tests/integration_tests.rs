@@ -2,6 +2,8 @@ extern crate kdtree;
 extern crate rand;
 
 use rand::Rng;
+use std::sync::Arc;
+use std::thread;
 
 use kdtree::test_common::*;
 use kdtree::KdtreePointTrait;
@@ -49,7 +51,7 @@ fn test_against_1000_random_points() {
     let points = generate_points(point_count);
     kdtree::test_common::Point1WithId::new(0, 0.);
 
-    let tree = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+    let tree = kdtree::Kdtree::from_vec(points.clone()).unwrap();
 
     //test points pushed into the tree, id should be equal.
     for i in 0..point_count {
@@ -74,7 +76,7 @@ fn test_incrementally_build_tree_against_built_at_once() {
     let point_count = 2000usize;
     let mut points = generate_points(point_count);
 
-    let tree_built_at_once = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+    let tree_built_at_once = kdtree::Kdtree::from_vec(points.clone()).unwrap();
     let mut tree_built_incrementally = kdtree::Kdtree::new(&mut points[0..1]).unwrap();
 
     for i in 1..point_count {
@@ -82,6 +84,7 @@ fn test_incrementally_build_tree_against_built_at_once() {
 
         tree_built_incrementally.insert_node(p.clone());
     }
+    assert!(tree_built_incrementally.validate().is_ok());
 
     //test points pushed into the tree, id should be equal.
     for i in 0..point_count {
@@ -117,7 +120,7 @@ fn test_within_1000_random_points() {
         for i in 0..point_count {
             points.push(Point3WithId::new(i as i32, i as f64, 0.0, 0.0));
         }
-        let mykdtree = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+        let mykdtree = kdtree::Kdtree::from_vec(points.clone()).unwrap();
 
         // Linear mapping of points
         for i in 0..point_count {
@@ -131,7 +134,7 @@ fn test_within_1000_random_points() {
         for i in 0..point_count {
             points.push(Point3WithId::new(i as i32, i as f64, i as f64, 0.0));
         }
-        let mykdtree = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+        let mykdtree = kdtree::Kdtree::from_vec(points.clone()).unwrap();
 
         // flat diagonal mapping of points
         for i in 0..point_count {
@@ -145,7 +148,7 @@ fn test_within_1000_random_points() {
         for i in 0..point_count {
             points.push(Point3WithId::new(i as i32, i as f64, i as f64, i as f64));
         }
-        let mykdtree = kdtree::Kdtree::new(&mut points.clone()).unwrap();
+        let mykdtree = kdtree::Kdtree::from_vec(points.clone()).unwrap();
 
         // flat diagonal mapping of points
         for i in 0..point_count {
@@ -154,3 +157,71 @@ fn test_within_1000_random_points() {
         }
     }
 }
+
+#[test]
+fn test_concurrent_queries_on_shared_tree() {
+    let points = Arc::new(generate_points(2000));
+    let tree = Arc::new(kdtree::Kdtree::from_vec((*points).clone()).unwrap());
+
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let points = points.clone();
+            let tree = tree.clone();
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let p = Point3WithId::new(0i32, gen_random(), gen_random(), gen_random());
+                    let expected = find_nn_with_linear_search(&points, p);
+                    assert_eq!(expected.id, tree.nearest_search(&p).id);
+                    assert!(
+                        tree.within(&p, 250000., &kdtree::distance::squared_euclidean)
+                            .len()
+                            <= points.len()
+                    );
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[test]
+fn test_concurrent_tree_readers_during_writes() {
+    let points = generate_points(1000);
+    let tree = Arc::new(kdtree::ConcurrentKdtree::new(
+        kdtree::Kdtree::from_vec(points.clone()).unwrap(),
+    ));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let points = points.clone();
+            let tree = tree.clone();
+            thread::spawn(move || {
+                for p in points.iter().cycle().take(5000) {
+                    // the original points are never removed, whatever snapshot is current.
+                    assert_eq!(p.id, tree.snapshot().nearest_search(p).id);
+                }
+            })
+        })
+        .collect();
+
+    for i in 0..200 {
+        let far_away = Point3WithId::new(1000 + i, 20000. + i as f64, 0., 0.);
+        tree.insert_node(far_away);
+        if i % 50 == 0 {
+            tree.update(|t| t.gather_points_and_rebuild());
+        }
+    }
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(
+        1199,
+        tree.snapshot()
+            .nearest_search(&Point3WithId::new(0, 30000., 0., 0.))
+            .id
+    );
+}